@@ -0,0 +1,385 @@
+use crate::crack::crack_hash_in_tables;
+use crate::error::{decode_hash, Result};
+use crate::generate::{cancelled, worker_count};
+use crate::hash::HashAlgorithm;
+use crate::progress::{count_lines, ProgressReporter, ProgressStyle};
+use crate::sort_merge::crack_targets_sort_merge;
+use crate::table::RainbowTable;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// 一括解析での1行ごとの結果
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BatchStatus {
+    // 復元できた
+    Cracked(String),
+    // テーブルに見つからなかった
+    NotCracked,
+    // 16進数として解釈できない、またはダイジェスト長が異なる
+    Invalid,
+    // 中断したため解析していない
+    Skipped,
+}
+
+// 一括解析の方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BatchEngine {
+    // ハッシュ値ごとに crack_hash と同じ探索を行う
+    #[default]
+    PerHash,
+    // 全ハッシュ値・全位置の候補終端ハッシュをソートし、テーブルと1回の順次走査で突き合わせる
+    // ハッシュ値が多い場合にテーブルへのランダムアクセスを避けられる
+    SortMerge,
+}
+
+// 一括解析のオプション
+#[derive(Clone, Debug, Default)]
+pub struct BatchOptions {
+    // 解析の方式
+    pub engine: BatchEngine,
+    // スレッド数（0 の場合は利用可能なCPU数）
+    pub threads: usize,
+    // 中断の要求（立つと解析中のハッシュ値を終えてから、残りを解析せずに返す）
+    pub cancel: Option<Arc<AtomicBool>>,
+    // 進捗表示の方法
+    pub progress: ProgressStyle,
+    // 入力の行数（残り時間の見積もりに使う、ファイルから読む場合は省略すると数える）
+    pub total_lines: Option<u64>,
+}
+
+// 一括解析の集計
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    // 入力の行数（空行を含む）
+    pub lines: usize,
+    // 重複を除いた有効なハッシュ値の数
+    pub unique: usize,
+    // 復元できたハッシュ値の数（重複を除く）
+    pub cracked: usize,
+    // 無効な行の数
+    pub invalid: usize,
+    // 中断したため解析しなかったハッシュ値の数（重複を除く）
+    pub skipped: usize,
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} 行（重複を除いて {} 件）のうち {} 件を復元、{} 件は未復元、無効な行 {} 行",
+            self.lines,
+            self.unique,
+            self.cracked,
+            self.unique - self.cracked - self.skipped,
+            self.invalid
+        )?;
+        if self.skipped > 0 {
+            write!(f, "、中断のため未解析 {} 件", self.skipped)?;
+        }
+        Ok(())
+    }
+}
+
+// ハッシュ値の表記を揃える（前後の空白を除き小文字にする）
+fn normalize_hash(line: &str) -> String {
+    line.trim().to_ascii_lowercase()
+}
+
+// 入力の読み込み元から一度に解析する行数
+// ハッシュ値ごとの解析ではこの行数ごとに結果を書き出し、進捗を表示する（ソートマージは入力全体をまとめて解析する）
+const STREAM_CHUNK_LINES: usize = 256;
+
+fn chunk_lines(engine: BatchEngine) -> usize {
+    match engine {
+        BatchEngine::PerHash => STREAM_CHUNK_LINES,
+        BatchEngine::SortMerge => usize::MAX,
+    }
+}
+
+// 解析済みのハッシュ値を覚えておき、重複したハッシュ値は再解析せずに行ごとの結果を返す
+struct BatchCracker<'a, H: ?Sized> {
+    rainbow_tables: &'a [RainbowTable],
+    hasher: &'a H,
+    options: &'a BatchOptions,
+    results: HashMap<String, Option<String>>,
+    summary: BatchSummary,
+    reporter: ProgressReporter,
+}
+
+impl<'a, H: HashAlgorithm + ?Sized> BatchCracker<'a, H> {
+    fn new(
+        rainbow_tables: &'a [RainbowTable],
+        hasher: &'a H,
+        options: &'a BatchOptions,
+        total_lines: Option<u64>,
+    ) -> Self {
+        // ハッシュ値ごとの解析は行単位、ソートマージはハッシュ値とテーブルの組単位で進捗を表示する
+        // （ソートマージは入力全体をまとめて解析するため、総数は重複を除いた後に決まる）
+        // ソートマージは組ごとに全位置の候補を計算するため、1組あたりのハッシュ計算回数が決まる
+        // （行単位では無効な行や重複、途中で復元できた場合の回数が分からないため表示しない）
+        let reporter = match options.engine {
+            BatchEngine::PerHash => {
+                ProgressReporter::new(options.progress, "解析", "行", total_lines)
+            }
+            BatchEngine::SortMerge => {
+                let hashes: u64 = rainbow_tables
+                    .iter()
+                    .map(|rainbow_table| {
+                        let chain_length = rainbow_table.params().chain_length as u64;
+                        chain_length * (chain_length + 1) / 2
+                    })
+                    .sum();
+                ProgressReporter::new(options.progress, "解析", "ハッシュ値", None)
+                    .with_hashes_per_unit(hashes / rainbow_tables.len().max(1) as u64)
+            }
+        };
+        BatchCracker {
+            rainbow_tables,
+            hasher,
+            options,
+            results: HashMap::new(),
+            summary: BatchSummary::default(),
+            reporter,
+        }
+    }
+
+    fn crack_lines(&mut self, lines: &[String]) -> Result<Vec<BatchStatus>> {
+        self.summary.lines += lines.len();
+
+        // まだ解析していない有効なハッシュ値を重複を除いて集める
+        let mut targets = Vec::new();
+        let mut pending = HashSet::new();
+        for line in lines {
+            let target_hash = normalize_hash(line);
+            if !self.is_valid(&target_hash) {
+                self.summary.invalid += 1;
+            } else if !self.results.contains_key(&target_hash)
+                && pending.insert(target_hash.clone())
+            {
+                targets.push(target_hash);
+            }
+        }
+        self.summary.unique += targets.len();
+
+        let (rainbow_tables, hasher, options) = (self.rainbow_tables, self.hasher, self.options);
+        let found = match options.engine {
+            BatchEngine::PerHash => crack_per_hash(rainbow_tables, hasher, &targets, options)?,
+            BatchEngine::SortMerge => crack_sort_merge(
+                rainbow_tables,
+                hasher,
+                &targets,
+                options,
+                &mut self.reporter,
+            )?,
+        };
+        // 中断のため解析しなかったハッシュ値は結果に含めない
+        for (target_hash, found) in targets.into_iter().zip(found) {
+            match found {
+                Some(found) => {
+                    self.summary.cracked += found.is_some() as usize;
+                    self.results.insert(target_hash, found);
+                }
+                None => self.summary.skipped += 1,
+            }
+        }
+
+        if options.engine == BatchEngine::PerHash {
+            self.reporter.update(self.summary.lines as u64);
+        }
+
+        Ok(lines
+            .iter()
+            .map(|line| {
+                let target_hash = normalize_hash(line);
+                match self.results.get(&target_hash) {
+                    Some(Some(plaintext)) => BatchStatus::Cracked(plaintext.clone()),
+                    Some(None) => BatchStatus::NotCracked,
+                    None if self.is_valid(&target_hash) => BatchStatus::Skipped,
+                    None => BatchStatus::Invalid,
+                }
+            })
+            .collect())
+    }
+
+    fn is_valid(&self, target_hash: &str) -> bool {
+        hex::decode(target_hash).is_ok_and(|bytes| bytes.len() == self.hasher.digest_len())
+    }
+}
+
+// ハッシュ値のリストを重複を除いて解析し、入力の行ごとの結果を返す
+pub fn crack_hash_list<H: HashAlgorithm + ?Sized>(
+    rainbow_tables: &[RainbowTable],
+    hasher: &H,
+    lines: &[String],
+    options: &BatchOptions,
+) -> Result<(Vec<BatchStatus>, BatchSummary)> {
+    let mut cracker = BatchCracker::new(rainbow_tables, hasher, options, Some(lines.len() as u64));
+    let mut statuses = Vec::with_capacity(lines.len());
+    for chunk in lines.chunks(chunk_lines(options.engine)) {
+        statuses.extend(cracker.crack_lines(chunk)?);
+    }
+    cracker.reporter.finish();
+    Ok((statuses, cracker.summary))
+}
+
+// 異なるハッシュ値を複数スレッドに分担して1件ずつ解析する
+// 戻り値はターゲットと同じ順序で、中断のため解析しなかったものは None
+fn crack_per_hash<H: HashAlgorithm + ?Sized>(
+    rainbow_tables: &[RainbowTable],
+    hasher: &H,
+    targets: &[String],
+    options: &BatchOptions,
+) -> Result<Vec<Option<Option<String>>>> {
+    // 次に解析するハッシュ値の番号を共有し、空いたスレッドから順に取っていく
+    let results = Mutex::new((0..targets.len()).map(|_| None).collect::<Vec<_>>());
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..worker_count(options.threads).min(targets.len()) {
+            scope.spawn(|| loop {
+                if cancelled(&options.cancel) {
+                    return;
+                }
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(target_hash) = targets.get(i) else {
+                    return;
+                };
+                let found = crack_hash_in_tables(rainbow_tables, hasher, target_hash, 1);
+                results.lock().unwrap()[i] = Some(found);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(Option::transpose)
+        .collect()
+}
+
+// テーブルごとにソートマージで解析し、まだ復元できていないハッシュ値だけを次のテーブルに回す
+// 中断した場合、残りのテーブルを照合していないハッシュ値は None
+// 進捗はハッシュ値とテーブルの組の数で表し、前のテーブルで復元できて照合を省いた組も済んだものとして数える
+fn crack_sort_merge<H: HashAlgorithm + ?Sized>(
+    rainbow_tables: &[RainbowTable],
+    hasher: &H,
+    targets: &[String],
+    options: &BatchOptions,
+    reporter: &mut ProgressReporter,
+) -> Result<Vec<Option<Option<String>>>> {
+    let target_bytes = targets
+        .iter()
+        .map(|target_hash| decode_hash(target_hash, hasher.digest_len()))
+        .collect::<Result<Vec<Vec<u8>>>>()?;
+    let mut results: Vec<Option<String>> = vec![None; targets.len()];
+    // 中断により照合していないテーブルが残ったハッシュ値
+    let mut skipped = vec![false; targets.len()];
+    let total = (targets.len() * rainbow_tables.len()) as u64;
+    reporter.set_total(Some(total));
+    let mut done = 0;
+    for rainbow_table in rainbow_tables {
+        let pending: Vec<usize> = (0..targets.len())
+            .filter(|&i| results[i].is_none() && !skipped[i])
+            .collect();
+        if pending.is_empty() {
+            reporter.update(total);
+            break;
+        }
+        done += (targets.len() - pending.len()) as u64;
+        let pending_bytes: Vec<Vec<u8>> =
+            pending.iter().map(|&i| target_bytes[i].clone()).collect();
+        let found = crack_targets_sort_merge(
+            rainbow_table,
+            hasher,
+            &pending_bytes,
+            options.threads,
+            &options.cancel,
+            &mut |checked| reporter.update(done + checked as u64),
+        )?;
+        done += pending.len() as u64;
+        for (i, found) in pending.into_iter().zip(found) {
+            match found {
+                Some(found) => results[i] = found,
+                None => skipped[i] = true,
+            }
+        }
+    }
+    Ok(results
+        .into_iter()
+        .zip(skipped)
+        .map(|(found, skipped)| (!skipped).then_some(found))
+        .collect())
+}
+
+// ハッシュ値のリストファイル（1行に1つ）を解析し、入力の行ごとに結果を書き出す
+// 出力はタブ区切りで「ハッシュ値 cracked 平文」「ハッシュ値 not_cracked」「行 invalid」
+pub fn crack_hash_file<H, P, Q>(
+    rainbow_tables: &[RainbowTable],
+    hasher: &H,
+    input: P,
+    output: Q,
+    options: &BatchOptions,
+) -> Result<BatchSummary>
+where
+    H: HashAlgorithm + ?Sized,
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let mut options = options.clone();
+    if options.total_lines.is_none() && options.progress != ProgressStyle::Silent {
+        options.total_lines = Some(count_lines(&input)?);
+    }
+    let reader = io::BufReader::new(File::open(input)?);
+    let writer = BufWriter::new(File::create(output)?);
+    crack_hash_reader(rainbow_tables, hasher, reader, writer, &options)
+}
+
+// 読み込み元のハッシュ値のリスト（1行に1つ）を解析し、入力の行ごとに結果を書き出す
+// ハッシュ値ごとの解析では、入力を読み切る前から解析の済んだ行の結果を順に書き出す
+// 中断した場合は、解析の済んだ先頭からの行の結果だけを書き出して返す
+pub fn crack_hash_reader<H, R, W>(
+    rainbow_tables: &[RainbowTable],
+    hasher: &H,
+    reader: R,
+    mut writer: W,
+    options: &BatchOptions,
+) -> Result<BatchSummary>
+where
+    H: HashAlgorithm + ?Sized,
+    R: BufRead,
+    W: Write,
+{
+    let chunk_lines = chunk_lines(options.engine);
+    let mut cracker = BatchCracker::new(rainbow_tables, hasher, options, options.total_lines);
+    let mut lines = reader.lines();
+    while !cancelled(&options.cancel) {
+        let chunk = lines
+            .by_ref()
+            .take(chunk_lines)
+            .collect::<io::Result<Vec<String>>>()?;
+        if chunk.is_empty() {
+            break;
+        }
+        let statuses = cracker.crack_lines(&chunk)?;
+        for (line, status) in chunk.iter().zip(&statuses) {
+            match status {
+                BatchStatus::Skipped => break,
+                BatchStatus::Cracked(plaintext) => {
+                    writeln!(writer, "{}\tcracked\t{}", normalize_hash(line), plaintext)?
+                }
+                BatchStatus::NotCracked => {
+                    writeln!(writer, "{}\tnot_cracked", normalize_hash(line))?
+                }
+                BatchStatus::Invalid => writeln!(writer, "{}\tinvalid", line.trim())?,
+            }
+        }
+        writer.flush()?;
+    }
+    cracker.reporter.finish();
+    Ok(cracker.summary)
+}
@@ -0,0 +1,173 @@
+use crate::error::{Error, Result};
+use crate::generate::GenerateOptions;
+use crate::table::{CollisionReport, TableParams};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// チェックポイントファイルの先頭に置くマジックナンバーと形式のバージョン
+const CHECKPOINT_MAGIC: &[u8; 4] = b"RBTC";
+// バージョン2で読み込み済みの行のダイジェストを追加した
+const CHECKPOINT_VERSION: u32 = 2;
+
+// テーブル生成の途中経過（バッチの区切りごとに更新する）
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct Progress {
+    // 計算済みのチェーン（完全テーブルでは終端ハッシュが一意なものだけ）
+    pub chains: Vec<(Vec<u8>, String)>,
+    // 読み込み済みのパスワードリストの行数
+    pub wordlist_lines: u64,
+    // 保存した時点までに読み込んだ行のSHA-1（再開時に同じパスワードリストか確かめる）
+    pub wordlist_sha1: Vec<u8>,
+    // 読み込んだ行のダイジェストの計算途中の状態（保存時に wordlist_sha1 に書き出す）
+    #[serde(skip)]
+    pub wordlist_digest: WordlistDigest,
+    // 以下は完全テーブルのみで使う
+    // パスワードリストを読み終えたか
+    pub wordlist_done: bool,
+    // 次にキースペースから追加する開始点の通し番号
+    pub keyspace_index: u128,
+    // 終端ハッシュが既出だったため捨てたチェーンの開始点
+    pub merged_starts: Vec<String>,
+    // 終端ハッシュの重複の集計
+    pub report: CollisionReport,
+}
+
+// 読み込んだパスワードリストの行のダイジェスト（各行を改行で終端して連結したもののSHA-1）
+#[derive(Clone, Default)]
+pub(crate) struct WordlistDigest(Sha1);
+
+impl WordlistDigest {
+    pub(crate) fn update(&mut self, lines: &[String]) {
+        for line in lines {
+            self.0.update(line.as_bytes());
+            self.0.update(b"\n");
+        }
+    }
+
+    pub(crate) fn value(&self) -> Vec<u8> {
+        self.0.clone().finalize().to_vec()
+    }
+}
+
+// チェックポイントの保存と再開
+// 生成結果に影響するパラメータも保存し、異なる設定での再開を拒否する
+// 生成が完了してもチェックポイントは削除しない（テーブルを保存できてから呼び出し側で削除する）
+pub(crate) struct Checkpointer<'a> {
+    path: Option<&'a Path>,
+    interval: Duration,
+    last_saved: Instant,
+    params: &'a TableParams,
+    perfect: bool,
+    target_chains: Option<u64>,
+}
+
+impl<'a> Checkpointer<'a> {
+    pub(crate) fn new(params: &'a TableParams, options: &'a GenerateOptions) -> Self {
+        Checkpointer {
+            path: options.checkpoint.as_deref(),
+            interval: options.checkpoint_interval,
+            last_saved: Instant::now(),
+            params,
+            perfect: options.perfect,
+            target_chains: options.target_chains.map(|n| n as u64),
+        }
+    }
+
+    // チェックポイントがあれば読み込む（なければ None）
+    // 読み込み済みの行は呼び出し側で読み飛ばし、verify_wordlist で同じパスワードリストか確かめる
+    pub(crate) fn resume(&self) -> Result<Option<Progress>> {
+        let Some(path) = self.path else {
+            return Ok(None);
+        };
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut reader = BufReader::new(file);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        let version = u32::from_le_bytes([magic[4], magic[5], magic[6], magic[7]]);
+        if &magic[..4] != CHECKPOINT_MAGIC {
+            return Err(Error::CorruptTable(format!(
+                "チェックポイントファイルではありません: {}",
+                path.display()
+            )));
+        }
+        if version != CHECKPOINT_VERSION {
+            return Err(Error::CorruptTable(format!(
+                "未対応のチェックポイントのバージョンです: {}（削除して最初から生成してください）",
+                version
+            )));
+        }
+
+        let params: TableParams = bincode::deserialize_from(&mut reader)?;
+        let (perfect, target_chains): (bool, Option<u64>) = bincode::deserialize_from(&mut reader)?;
+        let mismatches = params.mismatches(self.params);
+        if !mismatches.is_empty() {
+            return Err(Error::TableMismatch(format!(
+                "チェックポイントの生成パラメータが異なります（チェックポイント ≠ 指定値）: {}",
+                mismatches.join(", ")
+            )));
+        }
+        if perfect != self.perfect || target_chains != self.target_chains {
+            return Err(Error::TableMismatch(
+                "チェックポイントと完全テーブルの指定が異なります".to_string(),
+            ));
+        }
+        Ok(Some(bincode::deserialize_from(&mut reader)?))
+    }
+
+    // 再開時に読み飛ばした行が、チェックポイントを保存したときに読み込んだ行と同じか確かめる
+    pub(crate) fn verify_wordlist(progress: &Progress) -> Result<()> {
+        if progress.wordlist_digest.value() != progress.wordlist_sha1 {
+            return Err(Error::TableMismatch(
+                "パスワードリストの内容がチェックポイントを保存したときと異なります".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // 前回の保存から間隔が空いていれば途中経過を保存
+    pub(crate) fn update(&mut self, progress: &mut Progress) -> Result<()> {
+        if self.last_saved.elapsed() < self.interval {
+            return Ok(());
+        }
+        self.save(progress)
+    }
+
+    // 間隔によらず途中経過を保存（中断時など）
+    pub(crate) fn save(&mut self, progress: &mut Progress) -> Result<()> {
+        let Some(path) = self.path else {
+            return Ok(());
+        };
+        progress.wordlist_sha1 = progress.wordlist_digest.value();
+
+        // 書き込み中に中断されても前回のチェックポイントが残るよう、一時ファイルから置き換える
+        let temp_path = temp_path(path);
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        writer.write_all(CHECKPOINT_MAGIC)?;
+        writer.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, self.params)?;
+        bincode::serialize_into(&mut writer, &(self.perfect, self.target_chains))?;
+        bincode::serialize_into(&mut writer, &*progress)?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        fs::rename(&temp_path, path)?;
+        self.last_saved = Instant::now();
+        Ok(())
+    }
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut temp_path = OsString::from(path);
+    temp_path.push(".tmp");
+    PathBuf::from(temp_path)
+}
@@ -0,0 +1,664 @@
+use crate::error::{Error, Result};
+use crate::mapped::{corrupt, stored_index, stored_text, write_header, Cursor, TableBytes};
+use crate::table::{RainbowTable, StoredStart, TableHeader};
+use std::io::Write;
+use std::ops::Range;
+
+// 圧縮形式のファイル先頭に置くマジックナンバーと形式のバージョン
+pub(crate) const COMPRESSED_MAGIC: &[u8; 4] = b"RBTR";
+const COMPRESSED_VERSION: u32 = 1;
+
+// 1つのバケットに入るチェーン数の目安（実際は64〜128、照合時はこのうち平均半分を復号する）
+const CHAINS_PER_BUCKET: usize = 64;
+// 整数として差分を符号化する終端ハッシュの先頭のバイト数（残りはそのまま保存する）
+const KEY_BYTES: usize = 8;
+// 粗い索引1つ分のバイト数（先頭のチェーン番号、符号のビット位置、開始プレインテキストの位置を u64 で）
+const COARSE_ENTRY_LEN: usize = 24;
+// バケットの索引1つ分のバイト数（同じ3つの値を、属する粗い索引からの差の u32 で）
+// チェーンあたり 12 / 64〜128 ≒ 0.1〜0.2 バイトになる
+const FINE_ENTRY_LEN: usize = 12;
+// 粗い索引1つあたりのバケット数の2の対数
+const COARSE_SHIFT: u32 = 10;
+
+// 終端ハッシュの先頭を整数（キー）とみなし、上位ビットでバケットに分け、残りのビットをバケット内の差分として符号化する
+#[derive(Clone, Copy)]
+struct Layout {
+    key_bytes: usize,
+    key_bits: u32,
+    bucket_bits: u32,
+    rice_bits: u32,
+}
+
+impl Layout {
+    fn new(digest_len: usize, chain_count: usize) -> Self {
+        let key_bytes = digest_len.min(KEY_BYTES);
+        let key_bits = key_bytes as u32 * 8;
+        let bucket_bits = (chain_count / CHAINS_PER_BUCKET)
+            .max(1)
+            .ilog2()
+            .min(key_bits);
+        // キーが一様に分布するとき、差分の平均は 2^(残りのビット数) / バケットあたりのチェーン数
+        let per_bucket = (chain_count >> bucket_bits).max(1);
+        let rice_bits = (key_bits - bucket_bits).saturating_sub(per_bucket.ilog2());
+        Layout {
+            key_bytes,
+            key_bits,
+            bucket_bits,
+            rice_bits,
+        }
+    }
+
+    fn buckets(&self) -> usize {
+        1 << self.bucket_bits
+    }
+
+    fn tail_len(&self, digest_len: usize) -> usize {
+        digest_len - self.key_bytes
+    }
+
+    // 終端ハッシュの先頭をビッグエンディアンの整数として取り出す
+    fn key(&self, end_hash: &[u8]) -> u64 {
+        let mut bytes = [0u8; KEY_BYTES];
+        bytes[KEY_BYTES - self.key_bytes..].copy_from_slice(&end_hash[..self.key_bytes]);
+        u64::from_be_bytes(bytes)
+    }
+
+    fn bucket(&self, key: u64) -> usize {
+        key.checked_shr(self.key_bits - self.bucket_bits)
+            .unwrap_or(0) as usize
+    }
+
+    // キーのうちバケット内で符号化する下位ビット
+    fn value(&self, key: u64) -> u64 {
+        key & u64::MAX
+            .checked_shr(64 - (self.key_bits - self.bucket_bits))
+            .unwrap_or(0)
+    }
+}
+
+// 圧縮形式のファイルの配置（整数はすべてリトルエンディアン）
+//   マジックナンバー(4) バージョン(u32) ヘッダの長さ(u64) ヘッダ(bincode)
+//   終端ハッシュのバイト長(u32) チェーン数(u64) バケットのビット数(u32) Rice符号のパラメータ(u32)
+//   開始点の番号のバイト数(u32、開始プレインテキストを保存する場合は0)
+//   粗い索引（バケット数 / 2^COARSE_SHIFT + 1 個、各 COARSE_ENTRY_LEN バイト、2^COARSE_SHIFT バケットごと）
+//   バケットの索引（バケット数 + 1 個、各 FINE_ENTRY_LEN バイト）
+//   符号のバイト数(u64) バケット内のキーの差分のRice符号（上位ビットから詰める）
+//   終端ハッシュの KEY_BYTES バイト目以降（チェーンごとに固定長）
+//   開始プレインテキスト（改行で終端して連結）、または開始点の番号（チェーンごとに固定長）
+// RainbowCrack の .rtc と同様に、昇順に並べた終端ハッシュの差分が小さいことを利用して縮める
+pub(crate) fn write_compressed<W: Write>(
+    rainbow_table: &RainbowTable,
+    writer: &mut W,
+) -> Result<()> {
+    let digest_len = rainbow_table.digest_len();
+    let layout = Layout::new(digest_len, rainbow_table.len());
+    // 開始点の番号は最大の番号が収まるバイト数で保存する（番号の位置はチェーン番号から求まるので索引には0を置く）
+    let index_width = if rainbow_table.header.start_source.is_indexed() {
+        let mut max_index = 0;
+        for (_, start) in rainbow_table.stored_chains() {
+            max_index = max_index.max(stored_index(start)?);
+        }
+        (u64::BITS - max_index.leading_zeros()).div_ceil(8).max(1) as usize
+    } else {
+        0
+    };
+
+    // バケットごとの（先頭のチェーン番号, 符号のビット位置, 開始プレインテキストの位置）
+    let mut index = Vec::with_capacity(layout.buckets() + 1);
+    let mut stream = BitWriter::default();
+    let mut start_offset = 0;
+    let mut previous = 0;
+    for (i, (end_hash, start)) in rainbow_table.stored_chains().enumerate() {
+        if let StoredStart::Text(start_text) = start {
+            if start_text.contains(&b'\n') {
+                return Err(Error::InvalidParameter(format!(
+                    "開始プレインテキストに改行が含まれています: {:?}",
+                    String::from_utf8_lossy(start_text)
+                )));
+            }
+        }
+        // 新しいバケットに入ったら（間の空のバケットも含めて）索引を追加し、差分の基準を0に戻す
+        let key = layout.key(&end_hash);
+        while index.len() <= layout.bucket(key) {
+            index.push([i, stream.bit_len, start_offset]);
+            previous = 0;
+        }
+        let value = layout.value(key);
+        stream.write_rice(value - previous, layout.rice_bits);
+        previous = value;
+        if let StoredStart::Text(start_text) = start {
+            start_offset += start_text.len() + 1;
+        }
+    }
+    while index.len() <= layout.buckets() {
+        index.push([rainbow_table.len(), stream.bit_len, start_offset]);
+    }
+
+    write_header(rainbow_table, COMPRESSED_MAGIC, COMPRESSED_VERSION, writer)?;
+    writer.write_all(&(digest_len as u32).to_le_bytes())?;
+    writer.write_all(&(rainbow_table.len() as u64).to_le_bytes())?;
+    writer.write_all(&layout.bucket_bits.to_le_bytes())?;
+    writer.write_all(&layout.rice_bits.to_le_bytes())?;
+    writer.write_all(&(index_width as u32).to_le_bytes())?;
+    for entry in index.iter().step_by(1 << COARSE_SHIFT) {
+        write_index_entry(writer, entry, |value| (value as u64).to_le_bytes())?;
+    }
+    for (bucket, entry) in index.iter().enumerate() {
+        let base = &index[bucket >> COARSE_SHIFT << COARSE_SHIFT];
+        let mut relative = [0; 3];
+        for k in 0..3 {
+            relative[k] = u32::try_from(entry[k] - base[k]).map_err(|_| {
+                Error::InvalidParameter(
+                    "バケットの索引に収まらないほどチェーンが偏っています".to_string(),
+                )
+            })?;
+        }
+        write_index_entry(writer, &relative, u32::to_le_bytes)?;
+    }
+    writer.write_all(&(stream.bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(&stream.bytes)?;
+    for (end_hash, _) in rainbow_table.stored_chains() {
+        writer.write_all(&end_hash[layout.key_bytes..])?;
+    }
+    for (_, start) in rainbow_table.stored_chains() {
+        if index_width == 0 {
+            writer.write_all(stored_text(start)?)?;
+            writer.write_all(b"\n")?;
+        } else {
+            writer.write_all(&stored_index(start)?.to_le_bytes()[..index_width])?;
+        }
+    }
+    Ok(())
+}
+
+fn write_index_entry<W: Write, T: Copy, const N: usize>(
+    writer: &mut W,
+    entry: &[T; 3],
+    to_bytes: impl Fn(T) -> [u8; N],
+) -> Result<()> {
+    for &value in entry {
+        writer.write_all(&to_bytes(value))?;
+    }
+    Ok(())
+}
+
+// 圧縮形式のチェーンを展開せずに参照する
+// 照合時は対象のバケットだけを復号する
+pub(crate) struct CompressedChains {
+    bytes: TableBytes,
+    digest_len: usize,
+    len: usize,
+    layout: Layout,
+    coarse: Range<usize>,
+    index: Range<usize>,
+    stream: Range<usize>,
+    tails: Range<usize>,
+    starts: Range<usize>,
+    // 開始点の番号のバイト数（開始プレインテキストを保存している場合は0）
+    index_width: usize,
+}
+
+impl CompressedChains {
+    // ヘッダと各領域の位置を読み取る
+    pub(crate) fn parse(bytes: TableBytes) -> Result<(TableHeader, Self)> {
+        let data = bytes.as_slice();
+        let mut cursor = Cursor::new(data);
+        let header = cursor.header(COMPRESSED_MAGIC, COMPRESSED_VERSION, "圧縮形式")?;
+        let digest_len = cursor.u32()? as usize;
+        let len = cursor.len()?;
+        let bucket_bits = cursor.u32()?;
+        let rice_bits = cursor.u32()?;
+        let index_width = cursor.u32()? as usize;
+        let mut layout = Layout::new(digest_len, len);
+        if digest_len == 0
+            || header.chain_count != len as u64
+            || bucket_bits > layout.key_bits.min(usize::BITS - 1)
+            || rice_bits > layout.key_bits - bucket_bits
+            || index_width > 8
+            || header.start_source.is_indexed() != (index_width != 0)
+        {
+            return Err(corrupt("チェーン数または符号化のパラメータが壊れています"));
+        }
+        layout.bucket_bits = bucket_bits;
+        layout.rice_bits = rice_bits;
+
+        let coarse_len = ((layout.buckets() >> COARSE_SHIFT) + 1) * COARSE_ENTRY_LEN;
+        let coarse = cursor.range(Some(coarse_len))?;
+        let index = cursor.range((layout.buckets() + 1).checked_mul(FINE_ENTRY_LEN))?;
+        let stream_len = cursor.len()?;
+        let stream = cursor.range(Some(stream_len))?;
+        let tails = cursor.range(len.checked_mul(layout.tail_len(digest_len)))?;
+        let starts = cursor.pos..data.len();
+        let compressed = CompressedChains {
+            bytes,
+            digest_len,
+            len,
+            layout,
+            coarse,
+            index,
+            stream,
+            tails,
+            starts,
+            index_width,
+        };
+        // 全体を読まずに済むよう、索引の検証は両端だけにする（途中の破損は照合時に一致なしとして扱う）
+        let last = compressed.index_entry(compressed.layout.buckets());
+        if compressed.index_entry(0) != (0, 0, 0)
+            || last.0 != len
+            || last.1 > stream_len.saturating_mul(8)
+            || (index_width == 0 && last.2 != compressed.starts.len())
+            || (index_width != 0 && Some(compressed.starts.len()) != len.checked_mul(index_width))
+        {
+            return Err(corrupt("バケットの索引が壊れています"));
+        }
+        Ok((header, compressed))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn digest_len(&self) -> usize {
+        self.digest_len
+    }
+
+    // i 番目のチェーンの終端ハッシュ（属するバケットを先頭から復号する）
+    pub(crate) fn endpoint(&self, i: usize) -> Vec<u8> {
+        let bucket = self.bucket_of_chain(i);
+        let first = self.index_entry(bucket).0;
+        let key = self.bucket_keys(bucket).nth(i - first).unwrap_or_default();
+        self.full_endpoint(key, i)
+    }
+
+    // 終端ハッシュが一致するチェーンの番号の範囲（保存している長さより短いハッシュ値は一致なし）
+    pub(crate) fn find(&self, end_hash: &[u8]) -> Range<usize> {
+        if end_hash.len() < self.digest_len {
+            return 0..0;
+        }
+        let key = self.layout.key(end_hash);
+        let bucket = self.layout.bucket(key);
+        let first = self.index_entry(bucket).0;
+        let tail = &end_hash[self.layout.key_bytes..];
+        let mut matches = first..first;
+        for (i, stored) in (first..).zip(self.bucket_keys(bucket)) {
+            // バケット内は昇順なので、キーが超えたら打ち切る
+            if stored > key {
+                break;
+            }
+            if stored == key && self.tail(i) == tail {
+                if matches.is_empty() {
+                    matches.start = i;
+                }
+                matches.end = i + 1;
+            }
+        }
+        matches
+    }
+
+    // i 番目のチェーンの開始点
+    // 開始プレインテキストは属するバケットの先頭から改行を数えて探す
+    pub(crate) fn start(&self, i: usize) -> StoredStart<'_> {
+        let starts = &self.bytes.as_slice()[self.starts.clone()];
+        if self.index_width != 0 {
+            let mut bytes = [0u8; 8];
+            bytes[..self.index_width]
+                .copy_from_slice(&starts[i * self.index_width..(i + 1) * self.index_width]);
+            return StoredStart::Index(u64::from_le_bytes(bytes));
+        }
+        let bucket = self.bucket_of_chain(i);
+        let (first, _, start_offset) = self.index_entry(bucket);
+        starts
+            .get(start_offset..)
+            .and_then(|starts| starts.split(|&b| b == b'\n').nth(i - first))
+            .map_or(StoredStart::Text(b""), StoredStart::Text)
+    }
+
+    // （終端ハッシュ, 開始点）を先頭から順に復号する
+    // バケットごとに1回だけ復号するので、全体の走査では endpoint・start を繰り返すより速い
+    pub(crate) fn chains(&self) -> impl Iterator<Item = (Vec<u8>, StoredStart<'_>)> + '_ {
+        let mut start_texts = (self.index_width == 0)
+            .then(|| self.bytes.as_slice()[self.starts.clone()].split(|&b| b == b'\n'));
+        (0..self.layout.buckets())
+            .flat_map(move |bucket| {
+                let first = self.index_entry(bucket).0;
+                let next = self.index_entry(bucket + 1).0.min(self.len);
+                let mut keys = self.bucket_keys(bucket);
+                (first..next).map(move |i| (i, keys.next().unwrap_or_default()))
+            })
+            .map(move |(i, key)| {
+                let start = match &mut start_texts {
+                    Some(start_texts) => StoredStart::Text(start_texts.next().unwrap_or_default()),
+                    None => self.start(i),
+                };
+                (self.full_endpoint(key, i), start)
+            })
+    }
+
+    fn full_endpoint(&self, key: u64, i: usize) -> Vec<u8> {
+        let mut end_hash = key.to_be_bytes()[KEY_BYTES - self.layout.key_bytes..].to_vec();
+        end_hash.extend_from_slice(self.tail(i));
+        end_hash
+    }
+
+    fn tail(&self, i: usize) -> &[u8] {
+        let tail_len = self.layout.tail_len(self.digest_len);
+        let start = i * tail_len;
+        self.bytes.as_slice()[self.tails.clone()]
+            .get(start..start + tail_len)
+            .unwrap_or_default()
+    }
+
+    // バケットの索引（先頭のチェーン番号、符号のビット位置、開始プレインテキストの位置）
+    // 粗い索引の値にバケットの索引の差を足す
+    fn index_entry(&self, bucket: usize) -> (usize, usize, usize) {
+        let data = self.bytes.as_slice();
+        let wide = |pos: usize, k: usize| {
+            let bytes = data[pos + k * 8..pos + k * 8 + 8].try_into().unwrap();
+            usize::try_from(u64::from_le_bytes(bytes)).unwrap_or(usize::MAX)
+        };
+        let base = self.coarse.start + (bucket >> COARSE_SHIFT) * COARSE_ENTRY_LEN;
+        let pos = self.index.start + bucket * FINE_ENTRY_LEN;
+        let value = |k: usize| {
+            let bytes = data[pos + k * 4..pos + k * 4 + 4].try_into().unwrap();
+            wide(base, k).saturating_add(u32::from_le_bytes(bytes) as usize)
+        };
+        (value(0), value(1), value(2))
+    }
+
+    // i 番目のチェーンを含むバケット（先頭のチェーン番号が i 以下の最後のバケット）
+    fn bucket_of_chain(&self, i: usize) -> usize {
+        let (mut low, mut high) = (0, self.layout.buckets());
+        while low < high {
+            let mid = low + (high - low).div_ceil(2);
+            if self.index_entry(mid).0 <= i {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        low
+    }
+
+    // バケット内のキーを昇順に復号する
+    fn bucket_keys(&self, bucket: usize) -> impl Iterator<Item = u64> + '_ {
+        let (first, bit_offset, _) = self.index_entry(bucket);
+        let next = self.index_entry(bucket + 1).0;
+        let base = (bucket as u64)
+            .checked_shl(self.layout.key_bits - self.layout.bucket_bits)
+            .unwrap_or(0);
+        let mut reader = BitReader {
+            bytes: &self.bytes.as_slice()[self.stream.clone()],
+            bit_pos: bit_offset,
+        };
+        let mut value = 0u64;
+        (0..next.saturating_sub(first)).map_while(move |_| {
+            value = value.checked_add(reader.read_rice(self.layout.rice_bits)?)?;
+            Some(base | value)
+        })
+    }
+}
+
+// 上位ビットから詰めて書き込む
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_len: usize,
+}
+
+impl BitWriter {
+    fn write_bit(&mut self, bit: bool) {
+        if self.bit_len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bit_len % 8);
+        }
+        self.bit_len += 1;
+    }
+
+    // Rice符号（商を1の並びと0で、余りを下位 rice_bits ビットで表す）
+    fn write_rice(&mut self, value: u64, rice_bits: u32) {
+        for _ in 0..value.checked_shr(rice_bits).unwrap_or(0) {
+            self.write_bit(true);
+        }
+        self.write_bit(false);
+        for k in (0..rice_bits).rev() {
+            self.write_bit(value >> k & 1 == 1);
+        }
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    bit_pos: usize,
+}
+
+impl BitReader<'_> {
+    // 1の並びを0まで読み、1の個数を返す（バイト単位でまとめて数える）
+    fn read_unary(&mut self) -> Option<u64> {
+        let mut count = 0u64;
+        loop {
+            let byte = self.bytes.get(self.bit_pos / 8)?;
+            let offset = self.bit_pos % 8;
+            let ones = (byte << offset).leading_ones() as usize;
+            if ones < 8 - offset {
+                self.bit_pos += ones + 1;
+                return Some(count + ones as u64);
+            }
+            count += (8 - offset) as u64;
+            self.bit_pos += 8 - offset;
+        }
+    }
+
+    // 上位ビットから bits ビット（64以下）を読む
+    fn read_bits(&mut self, bits: u32) -> Option<u64> {
+        let mut value = 0u64;
+        let mut remaining = bits;
+        while remaining > 0 {
+            let byte = self.bytes.get(self.bit_pos / 8)?;
+            let offset = (self.bit_pos % 8) as u32;
+            let take = remaining.min(8 - offset);
+            value = value << take | ((byte << offset) >> (8 - take)) as u64;
+            self.bit_pos += take as usize;
+            remaining -= take;
+        }
+        Some(value)
+    }
+
+    // 壊れた符号で範囲外を読もうとした場合は None
+    fn read_rice(&mut self, rice_bits: u32) -> Option<u64> {
+        let quotient = self.read_unary()?;
+        if quotient != 0 && quotient.leading_zeros() < rice_bits {
+            return None;
+        }
+        let remainder = self.read_bits(rice_bits)?;
+        Some(quotient.checked_shl(rice_bits).unwrap_or(0) | remainder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::starts::StartSource;
+    use crate::table::{CollisionPolicy, TableParams};
+    use sha1::{Digest, Sha1};
+
+    // 番号から作る一様に分布した終端ハッシュ
+    fn end_hash(i: u64) -> Vec<u8> {
+        Sha1::digest(i.to_le_bytes()).to_vec()
+    }
+
+    fn header() -> TableHeader {
+        TableHeader::new(TableParams::default(), 0)
+    }
+
+    // 終端ハッシュ番号ごとの（終端ハッシュ, 開始プレインテキスト）からテーブルを作る
+    fn text_table(ends: impl Iterator<Item = u64>) -> RainbowTable {
+        let chains = ends
+            .enumerate()
+            .map(|(i, end)| (end_hash(end), format!("start{}", i)))
+            .collect();
+        RainbowTable::from_chains(header(), 20, chains, CollisionPolicy::KeepAll)
+            .unwrap()
+            .0
+    }
+
+    fn roundtrip(table: &RainbowTable) -> CompressedChains {
+        let mut bytes = Vec::new();
+        write_compressed(table, &mut bytes).unwrap();
+        let (header, compressed) = CompressedChains::parse(TableBytes::Owned(bytes)).unwrap();
+        assert_eq!(header.chain_count, table.len() as u64);
+        assert_eq!(header.start_source, table.header.start_source);
+        compressed
+    }
+
+    // すべてのチェーンの終端ハッシュと開始点が元のテーブルと同じで、終端ハッシュで探すと見つかる
+    fn assert_same_chains(table: &RainbowTable, compressed: &CompressedChains) {
+        assert_eq!(compressed.len(), table.len());
+        assert_eq!(compressed.digest_len(), table.digest_len());
+        assert_eq!(compressed.chains().count(), table.len());
+        for (i, (end_hash, start)) in compressed.chains().enumerate() {
+            assert_eq!(end_hash, *table.endpoint(i));
+            assert_eq!(start, table.stored_start(i));
+        }
+        for i in 0..table.len() {
+            assert_eq!(compressed.endpoint(i), *table.endpoint(i));
+            assert_eq!(compressed.start(i), table.stored_start(i));
+            let matches = compressed.find(&table.endpoint(i));
+            assert!(matches.contains(&i));
+            assert!(matches
+                .clone()
+                .all(|k| *table.endpoint(k) == *table.endpoint(i)));
+        }
+    }
+
+    #[test]
+    fn rice_code_roundtrip() {
+        for rice_bits in [0, 1, 5, 12, 31, 63, 64] {
+            // 商と余りの組み合わせ（余りの最大値を含む、rice_bits が64のときは商が0のみ）
+            let max_remainder = u64::MAX.checked_shr(64 - rice_bits).unwrap_or(0);
+            let quotients: &[u64] = if rice_bits == 64 {
+                &[0]
+            } else {
+                &[0, 1, 2, 7, 8, 255, 300]
+            };
+            let values: Vec<u64> = quotients
+                .iter()
+                .flat_map(|&quotient| {
+                    [0, 1, max_remainder / 3, max_remainder]
+                        .map(|remainder| (quotient << (rice_bits % 64)) | remainder)
+                })
+                .collect();
+            let mut writer = BitWriter::default();
+            for &value in &values {
+                writer.write_rice(value, rice_bits);
+            }
+            let mut reader = BitReader {
+                bytes: &writer.bytes,
+                bit_pos: 0,
+            };
+            for &value in &values {
+                assert_eq!(
+                    reader.read_rice(rice_bits),
+                    Some(value),
+                    "rice_bits {}",
+                    rice_bits
+                );
+            }
+            assert_eq!(reader.bit_pos, writer.bit_len);
+        }
+    }
+
+    #[test]
+    fn truncated_rice_code_is_none() {
+        let mut writer = BitWriter::default();
+        writer.write_rice(1000, 3);
+        let mut reader = BitReader {
+            bytes: &writer.bytes[..writer.bytes.len() - 1],
+            bit_pos: 0,
+        };
+        assert_eq!(reader.read_rice(3), None);
+    }
+
+    #[test]
+    fn empty_table() {
+        let table = text_table(0..0);
+        let compressed = roundtrip(&table);
+        assert_same_chains(&table, &compressed);
+        assert!(compressed.find(&end_hash(0)).is_empty());
+    }
+
+    #[test]
+    fn single_chain() {
+        let table = text_table(0..1);
+        let compressed = roundtrip(&table);
+        assert_same_chains(&table, &compressed);
+        assert!(compressed.find(&end_hash(1)).is_empty());
+    }
+
+    // 2段の索引の粗い索引が複数になるだけのチェーン数（バケット数が 2^COARSE_SHIFT を超える）
+    #[test]
+    fn many_chains_across_coarse_index() {
+        let count = CHAINS_PER_BUCKET << (COARSE_SHIFT + 1);
+        let table = text_table(0..count as u64);
+        let compressed = roundtrip(&table);
+        assert!(compressed.layout.buckets() > 1 << COARSE_SHIFT);
+        assert_same_chains(&table, &compressed);
+        for missing in count as u64..count as u64 + 100 {
+            assert!(compressed.find(&end_hash(missing)).is_empty());
+        }
+    }
+
+    #[test]
+    fn duplicate_endpoints() {
+        let table = text_table((0..2000).map(|i| i / 4));
+        let compressed = roundtrip(&table);
+        assert_same_chains(&table, &compressed);
+        for end in 0..500 {
+            assert_eq!(compressed.find(&end_hash(end)).len(), 4);
+        }
+    }
+
+    #[test]
+    fn indexed_starts() {
+        let header = TableHeader {
+            start_source: StartSource::Keyspace,
+            ..header()
+        };
+        let chains = (0..3000u64).map(|i| (end_hash(i), i * 1_000_003)).collect();
+        let table = RainbowTable::from_indexed_chains(header, 20, chains).unwrap();
+        let compressed = roundtrip(&table);
+        assert_eq!(compressed.index_width, 4);
+        assert_same_chains(&table, &compressed);
+    }
+
+    #[test]
+    fn truncated_endpoints() {
+        let table = text_table(0..3000);
+        for endpoint_len in [1, 3, 8, 12] {
+            let truncated = table.with_endpoint_len(endpoint_len).unwrap();
+            let compressed = roundtrip(&truncated);
+            assert_eq!(compressed.digest_len(), endpoint_len);
+            assert_same_chains(&truncated, &compressed);
+        }
+    }
+
+    #[test]
+    fn short_hash_finds_nothing() {
+        let table = text_table(0..100);
+        let compressed = roundtrip(&table);
+        for len in [0, 1, KEY_BYTES, 19] {
+            assert!(compressed.find(&table.endpoint(0)[..len]).is_empty());
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let table = text_table(0..3000);
+        let mut bytes = Vec::new();
+        write_compressed(&table, &mut bytes).unwrap();
+        for len in [0, 10, bytes.len() / 2] {
+            assert!(CompressedChains::parse(TableBytes::Owned(bytes[..len].to_vec())).is_err());
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use crate::error::{decode_hash, Error, Result};
+use crate::generate::worker_count;
+use crate::hash::HashAlgorithm;
+use crate::reduce::Reducer;
+use crate::table::RainbowTable;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// 開始プレインテキストからチェーンをたどり、ターゲットハッシュを生成する平文を探す
+fn walk_chain<H: HashAlgorithm + ?Sized>(
+    hasher: &H,
+    reducer: &Reducer,
+    chain_length: usize,
+    start_text: &str,
+    target_bytes: &[u8],
+) -> Option<String> {
+    let mut plaintext = start_text.to_string();
+    let mut digest = vec![0; hasher.digest_len()];
+    for k in 0..chain_length {
+        hasher.hash_into(plaintext.as_bytes(), &mut digest);
+        if digest == target_bytes {
+            return Some(plaintext);
+        }
+        reducer.reduce_into(&digest, k, &mut plaintext);
+    }
+    None
+}
+
+// ターゲットハッシュがチェーンの i 番目にあると仮定して終端までたどり、テーブルと照合する
+// 途中の位置のハッシュ値は終端ハッシュと比べる位置が違うので照合しない（誤警報が増えるだけのため）
+// stop が立った場合は途中で打ち切る
+fn search_position<H: HashAlgorithm + ?Sized>(
+    rainbow_table: &RainbowTable,
+    hasher: &H,
+    reducer: &Reducer,
+    target_bytes: &[u8],
+    i: usize,
+    stop: &AtomicBool,
+) -> Option<String> {
+    let chain_length = rainbow_table.params().chain_length;
+    let mut current_hash = target_bytes.to_vec();
+    let mut candidate_text = String::new();
+
+    // 各ステップでリダクションとハッシュを繰り返し、終端の位置のハッシュ値を求める
+    for j in i..chain_length {
+        if stop.load(Ordering::Relaxed) {
+            return None;
+        }
+        reducer.reduce_into(&current_hash, j, &mut candidate_text);
+        hasher.hash_into(candidate_text.as_bytes(), &mut current_hash);
+    }
+
+    // 一致したチェーンを開始からたどり、ターゲットハッシュと一致するか確認
+    rainbow_table
+        .find(&current_hash)
+        .find_map(|start_text| walk_chain(hasher, reducer, chain_length, &start_text, target_bytes))
+}
+
+// テーブルが hasher と同じハッシュアルゴリズムで生成されたか確認
+// （終端ハッシュを切り詰めたテーブルでは、その長さがハッシュ値の長さ以下であること）
+pub(crate) fn check_algorithm<H: HashAlgorithm + ?Sized>(
+    rainbow_table: &RainbowTable,
+    hasher: &H,
+) -> Result<()> {
+    if rainbow_table.params().algorithm != hasher.name() {
+        return Err(Error::TableMismatch(format!(
+            "テーブルのハッシュアルゴリズム {} と {} が一致しません",
+            rainbow_table.params().algorithm,
+            hasher.name()
+        )));
+    }
+    if rainbow_table.digest_len() > hasher.digest_len() {
+        return Err(Error::TableMismatch(format!(
+            "テーブルの終端ハッシュ（{} バイト）が {} のハッシュ値より長くなっています",
+            rainbow_table.digest_len(),
+            hasher.name()
+        )));
+    }
+    Ok(())
+}
+
+// ハッシュ値からプレインテキストを復元（見つからない場合は None）
+pub fn crack_hash<H: HashAlgorithm + ?Sized>(
+    rainbow_table: &RainbowTable,
+    hasher: &H,
+    target_hash: &str,
+) -> Result<Option<String>> {
+    crack_hash_with_threads(rainbow_table, hasher, target_hash, 1)
+}
+
+// チェーン上の位置ごとの探索を複数スレッドに分けてプレインテキストを復元
+// いずれかのスレッドが見つけた時点で他のスレッドも打ち切る（threads が 0 の場合は利用可能なCPU数）
+pub fn crack_hash_with_threads<H: HashAlgorithm + ?Sized>(
+    rainbow_table: &RainbowTable,
+    hasher: &H,
+    target_hash: &str,
+    threads: usize,
+) -> Result<Option<String>> {
+    check_algorithm(rainbow_table, hasher)?;
+    rainbow_table.check_starts()?;
+    let target_bytes = decode_hash(target_hash, hasher.digest_len())?;
+    let reducer = rainbow_table.reducer()?;
+    let chain_length = rainbow_table.params().chain_length;
+    let threads = worker_count(threads).min(chain_length.max(1));
+    let stop = AtomicBool::new(false);
+
+    // チェーンの逆方向から探索
+    if threads == 1 {
+        return Ok((0..chain_length).rev().find_map(|i| {
+            search_position(rainbow_table, hasher, &reducer, &target_bytes, i, &stop)
+        }));
+    }
+
+    // 各スレッドは終端側の位置から1つおきに担当し、計算量の少ない位置から先に調べる
+    let result = Mutex::new(None);
+    thread::scope(|scope| {
+        for t in 0..threads {
+            let (reducer, target_bytes, stop, result) = (&reducer, &target_bytes, &stop, &result);
+            scope.spawn(move || {
+                for i in (0..chain_length).rev().skip(t).step_by(threads) {
+                    if stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let found =
+                        search_position(rainbow_table, hasher, reducer, target_bytes, i, stop);
+                    if found.is_some() {
+                        stop.store(true, Ordering::Relaxed);
+                        *result.lock().unwrap() = found;
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    Ok(result.into_inner().unwrap())
+}
+
+// 複数のテーブル（それぞれ異なるテーブル番号）を順に照合してプレインテキストを復元
+pub fn crack_hash_in_tables<H: HashAlgorithm + ?Sized>(
+    rainbow_tables: &[RainbowTable],
+    hasher: &H,
+    target_hash: &str,
+    threads: usize,
+) -> Result<Option<String>> {
+    for rainbow_table in rainbow_tables {
+        let found = crack_hash_with_threads(rainbow_table, hasher, target_hash, threads)?;
+        if found.is_some() {
+            return Ok(found);
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::GenerateOptions;
+    use crate::hash::Sha1Hash;
+    use crate::test_util;
+
+    // 複数スレッドで探索しても1スレッドの場合と同じ結果になること
+    #[test]
+    fn threads_match_single_thread() {
+        let table = test_util::table(200, GenerateOptions::default());
+        let reducer = table.reducer().unwrap();
+        let chain_length = table.params().chain_length;
+        let mut targets: Vec<String> = (0..table.len())
+            .step_by(9)
+            .map(|k| {
+                let mut plaintext = table.start(k).into_owned();
+                for j in 0..k * 3 % chain_length {
+                    reducer.reduce_into(&Sha1Hash.hash(plaintext.as_bytes()), j, &mut plaintext);
+                }
+                hex::encode(Sha1Hash.hash(plaintext.as_bytes()))
+            })
+            .collect();
+        // キースペースの外の平文（7文字）
+        targets.push(hex::encode(Sha1Hash.hash(b"zzzzzzz")));
+
+        for target_hash in &targets {
+            let expected = crack_hash(&table, &Sha1Hash, target_hash).unwrap();
+            let actual = crack_hash_with_threads(&table, &Sha1Hash, target_hash, 4).unwrap();
+            assert_eq!(actual, expected, "{}", target_hash);
+        }
+        assert!(crack_hash(&table, &Sha1Hash, &targets[0])
+            .unwrap()
+            .is_some());
+        assert!(crack_hash(&table, &Sha1Hash, targets.last().unwrap())
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+use std::io;
+
+// このクレートの関数が返すエラー
+#[derive(Debug)]
+pub enum Error {
+    // ハッシュ値が16進数として解釈できない
+    InvalidHashEncoding(String),
+    // ハッシュ値の長さがアルゴリズムのダイジェスト長と異なる
+    WrongDigestLength { expected: usize, actual: usize },
+    // テーブルの生成パラメータやハッシュアルゴリズムが使用中のものと一致しない
+    TableMismatch(String),
+    // テーブルファイルの内容が壊れている、または未対応の形式
+    CorruptTable(String),
+    // キースペースやアルゴリズム名などの指定が不正
+    InvalidParameter(String),
+    // 入出力のエラー
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidHashEncoding(hash) => write!(f, "無効なハッシュ形式です: {:?}", hash),
+            Error::WrongDigestLength { expected, actual } => write!(
+                f,
+                "ハッシュ値の長さが {} バイトではなく {} バイトです",
+                expected, actual
+            ),
+            Error::TableMismatch(message) => write!(f, "テーブルが一致しません: {}", message),
+            Error::CorruptTable(message) => write!(f, "テーブルが壊れています: {}", message),
+            Error::InvalidParameter(message) => write!(f, "{}", message),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        if e.is_io() {
+            Error::Io(e.into())
+        } else {
+            Error::CorruptTable(e.to_string())
+        }
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(e: bincode::Error) -> Self {
+        match *e {
+            // 途中で切れたファイルは入出力ではなくテーブルの破損として扱う
+            bincode::ErrorKind::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                Error::CorruptTable("ファイルが途中で終わっています".to_string())
+            }
+            bincode::ErrorKind::Io(e) => Error::Io(e),
+            e => Error::CorruptTable(e.to_string()),
+        }
+    }
+}
+
+// 16進数のハッシュ値をダイジェスト長を確かめてバイト列に変換
+pub(crate) fn decode_hash(target_hash: &str, digest_len: usize) -> Result<Vec<u8>> {
+    let bytes = hex::decode(target_hash)
+        .map_err(|_| Error::InvalidHashEncoding(target_hash.to_string()))?;
+    if bytes.len() != digest_len {
+        return Err(Error::WrongDigestLength {
+            expected: digest_len,
+            actual: bytes.len(),
+        });
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,630 @@
+use crate::compressed::{write_compressed, CompressedChains, COMPRESSED_MAGIC};
+use crate::error::{self, Error};
+use crate::hash::algorithm_by_name;
+use crate::mapped::{write_mapped, MappedChains, TableBytes, MAPPED_MAGIC};
+use crate::starts::StartSource;
+use crate::table::{CollisionPolicy, RainbowTable, StoredStart, TableHeader, TableParams};
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+// バイナリ形式のファイル先頭に置くマジックナンバーと形式のバージョン
+const BINARY_MAGIC: &[u8; 4] = b"RBTB";
+const BINARY_VERSION: u32 = 1;
+
+// テーブルファイルの保存形式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableFormat {
+    // 終端ハッシュを16進数文字列で持つ従来のJSON形式
+    Json,
+    // 終端ハッシュを生のバイト列で持つbincode形式
+    Binary,
+    // 読み込まずにメモリマップしてそのまま二分探索できる形式
+    Mapped,
+    // 終端ハッシュの差分をRice符号で縮め、メモリマップしたまま照合できる形式
+    Compressed,
+}
+
+impl TableFormat {
+    // 拡張子が .json ならJSON形式、.rtm ならメモリマップ形式、.rtc なら圧縮形式、それ以外はバイナリ形式
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => TableFormat::Json,
+            Some(ext) if ext.eq_ignore_ascii_case("rtm") => TableFormat::Mapped,
+            Some(ext) if ext.eq_ignore_ascii_case("rtc") => TableFormat::Compressed,
+            _ => TableFormat::Binary,
+        }
+    }
+
+    // ファイル先頭のマジックナンバーから形式を判別
+    pub fn detect<P: AsRef<Path>>(path: P) -> error::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        Ok(Self::from_magic(file.fill_buf()?))
+    }
+
+    fn from_magic(head: &[u8]) -> Self {
+        if head.starts_with(BINARY_MAGIC) {
+            TableFormat::Binary
+        } else if head.starts_with(MAPPED_MAGIC) {
+            TableFormat::Mapped
+        } else if head.starts_with(COMPRESSED_MAGIC) {
+            TableFormat::Compressed
+        } else {
+            TableFormat::Json
+        }
+    }
+}
+
+// JSON形式の読み込み用の表現
+// ヘッダのないファイル（初期実装のJSON）は初期実装のパラメータで読み込む
+#[derive(Deserialize)]
+struct JsonTable {
+    #[serde(default)]
+    header: Option<TableHeader>,
+    table: HexChainList,
+}
+
+impl JsonTable {
+    fn into_table(self) -> error::Result<RainbowTable> {
+        let header = self.header.unwrap_or_else(|| TableHeader {
+            params: TableParams::legacy(),
+            chain_count: self.table.len() as u64,
+            created_at: 0,
+            partial: false,
+            start_source: StartSource::Plaintext,
+        });
+        let HexChainList { texts, indices } = self.table;
+        check_chain_count(&header, texts.len() + indices.len())?;
+
+        let digest_len = texts
+            .first()
+            .map(|(end_hash, _)| end_hash.len())
+            .or_else(|| indices.first().map(|(end_hash, _)| end_hash.len()))
+            .or_else(|| algorithm_by_name(&header.params.algorithm).map(|h| h.digest_len()))
+            .unwrap_or(0);
+        // 開始点はヘッダの start_source に合わせて、すべて文字列かすべて番号であること
+        if header.start_source.is_indexed() {
+            if !texts.is_empty() {
+                return Err(corrupt(
+                    "番号で保存したテーブルに開始プレインテキストがあります".to_string(),
+                ));
+            }
+            if !indices.is_sorted_by(|a, b| a.0 <= b.0) {
+                return RainbowTable::from_indexed_chains(header, digest_len, indices);
+            }
+            let endpoints = indices.iter().flat_map(|(end_hash, _)| end_hash).copied();
+            let endpoints = endpoints.collect();
+            let indices = indices.into_iter().map(|(_, index)| index).collect();
+            return RainbowTable::from_sorted_indices(header, digest_len, endpoints, indices);
+        }
+        if !indices.is_empty() {
+            return Err(corrupt(
+                "開始プレインテキストを保存したテーブルに番号があります".to_string(),
+            ));
+        }
+        // このツールで保存したファイルは終端ハッシュの昇順なのでそのまま使い、
+        // そうでないもの（初期実装のJSONなど）だけを並べ替える
+        if !texts.is_sorted_by(|a, b| a.0 <= b.0) {
+            let policy = CollisionPolicy::KeepAll;
+            return Ok(RainbowTable::from_chains(header, digest_len, texts, policy)?.0);
+        }
+        let endpoints = texts.iter().flat_map(|(end_hash, _)| end_hash).copied();
+        let endpoints = endpoints.collect();
+        let start_texts = texts.iter().map(|(_, start_text)| start_text.as_str());
+        RainbowTable::from_sorted_texts(header, digest_len, endpoints, start_texts)
+    }
+}
+
+// JSON形式の table（16進数文字列の終端ハッシュ → 開始プレインテキスト）を、
+// HashMap を経由せずに（終端ハッシュ, 開始プレインテキスト）の列として読み込む
+// 1つの終端ハッシュに複数のチェーンがある場合、値は開始プレインテキストの配列になる
+// 開始点を番号で保存したテーブルでは、開始プレインテキストの代わりに番号（整数）になる
+struct HexChainList {
+    texts: Vec<(Vec<u8>, String)>,
+    indices: Vec<(Vec<u8>, u64)>,
+}
+
+impl HexChainList {
+    fn len(&self) -> usize {
+        self.texts.len() + self.indices.len()
+    }
+}
+
+impl<'de> Deserialize<'de> for HexChainList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HexChainVisitor;
+
+        impl<'de> Visitor<'de> for HexChainVisitor {
+            type Value = HexChainList;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("16進数文字列の終端ハッシュをキーとするマップ")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<HexChainList, A::Error> {
+                let mut chains = HexChainList {
+                    texts: Vec::with_capacity(map.size_hint().unwrap_or(0)),
+                    indices: Vec::new(),
+                };
+                let mut digest_len = None;
+                while let Some((end_hash_hex, start_texts)) =
+                    map.next_entry::<String, StartTexts>()?
+                {
+                    let end_hash = hex::decode(&end_hash_hex).map_err(de::Error::custom)?;
+                    if *digest_len.get_or_insert(end_hash.len()) != end_hash.len() {
+                        return Err(de::Error::custom("終端ハッシュの長さが揃っていません"));
+                    }
+                    match start_texts {
+                        StartTexts::One(start_text) => chains.texts.push((end_hash, start_text)),
+                        StartTexts::Many(start_texts) => chains.texts.extend(
+                            start_texts
+                                .into_iter()
+                                .map(|start_text| (end_hash.clone(), start_text)),
+                        ),
+                        StartTexts::Index(index) => chains.indices.push((end_hash, index)),
+                        StartTexts::Indices(indices) => chains
+                            .indices
+                            .extend(indices.into_iter().map(|index| (end_hash.clone(), index))),
+                    }
+                }
+                Ok(chains)
+            }
+        }
+
+        deserializer.deserialize_map(HexChainVisitor)
+    }
+}
+
+// JSON形式の書き込み用の表現（終端ハッシュは16進数文字列のキーにする）
+#[derive(Serialize)]
+struct JsonTableRef<'a> {
+    header: &'a TableHeader,
+    table: HexChains<'a>,
+}
+
+struct HexChains<'a>(&'a RainbowTable);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StartTexts {
+    One(String),
+    Many(Vec<String>),
+    Index(u64),
+    Indices(Vec<u64>),
+}
+
+// 書き込む開始点（開始プレインテキストは文字列、番号は整数にする）
+#[derive(Serialize)]
+#[serde(untagged)]
+enum StartRef<'a> {
+    Text(Cow<'a, str>),
+    Index(u64),
+}
+
+impl<'a> From<StoredStart<'a>> for StartRef<'a> {
+    fn from(start: StoredStart<'a>) -> Self {
+        match start {
+            StoredStart::Text(start_text) => StartRef::Text(String::from_utf8_lossy(start_text)),
+            StoredStart::Index(index) => StartRef::Index(index),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum StartTextsRef<'a> {
+    One(&'a StartRef<'a>),
+    Many(Vec<&'a StartRef<'a>>),
+}
+
+impl Serialize for HexChains<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let chains: Vec<(Cow<[u8]>, StartRef)> = self
+            .0
+            .stored_chains()
+            .map(|(end_hash, start)| (end_hash, start.into()))
+            .collect();
+        serializer.collect_map(chains.chunk_by(|a, b| a.0 == b.0).map(|group| {
+            let start_texts = match group {
+                [(_, start)] => StartTextsRef::One(start),
+                _ => StartTextsRef::Many(group.iter().map(|(_, start)| start).collect()),
+            };
+            (hex::encode(&group[0].0), start_texts)
+        }))
+    }
+}
+
+// バイナリ形式の本体
+// 終端ハッシュは digest_len バイトずつ昇順に連結し、開始プレインテキストは改行で終端して連結する
+// 開始点を番号で保存したテーブルでは、開始プレインテキストの代わりに start_indices に番号を並べる
+#[derive(Serialize, Deserialize)]
+struct BinaryTable {
+    header: TableHeader,
+    digest_len: u32,
+    endpoints: Vec<u8>,
+    starts: Vec<u8>,
+    start_indices: Vec<u64>,
+}
+
+impl BinaryTable {
+    fn from_table(rainbow_table: &RainbowTable) -> error::Result<Self> {
+        let mut endpoints = Vec::with_capacity(rainbow_table.len() * rainbow_table.digest_len());
+        let mut starts = Vec::new();
+        let mut start_indices = Vec::new();
+        for (end_hash, start) in rainbow_table.stored_chains() {
+            endpoints.extend_from_slice(&end_hash);
+            match start {
+                StoredStart::Text(start_text) if start_text.contains(&b'\n') => {
+                    return Err(Error::InvalidParameter(format!(
+                        "開始プレインテキストに改行が含まれています: {:?}",
+                        String::from_utf8_lossy(start_text)
+                    )));
+                }
+                StoredStart::Text(start_text) => {
+                    starts.extend_from_slice(start_text);
+                    starts.push(b'\n');
+                }
+                StoredStart::Index(index) => start_indices.push(index),
+            }
+        }
+
+        Ok(BinaryTable {
+            header: rainbow_table.header.clone(),
+            digest_len: rainbow_table.digest_len() as u32,
+            endpoints,
+            starts,
+            start_indices,
+        })
+    }
+
+    fn into_table(self) -> error::Result<RainbowTable> {
+        if self.header.start_source.is_indexed() {
+            let digest_len = self.digest_len as usize;
+            if digest_len == 0
+                || self.endpoints.len() != self.start_indices.len() * digest_len
+                || !self.starts.is_empty()
+            {
+                return Err(corrupt("終端ハッシュの領域が壊れています".to_string()));
+            }
+            check_chain_count(&self.header, self.start_indices.len())?;
+            return RainbowTable::from_sorted_indices(
+                self.header,
+                digest_len,
+                self.endpoints,
+                self.start_indices,
+            );
+        }
+
+        let starts = String::from_utf8(self.starts).map_err(|e| corrupt(e.to_string()))?;
+        let digest_len = self.digest_len as usize;
+        let start_texts: Vec<&str> = starts.split_terminator('\n').collect();
+        if digest_len == 0
+            || self.endpoints.len() != start_texts.len() * digest_len
+            || !starts.is_empty() && !starts.ends_with('\n')
+        {
+            return Err(corrupt("終端ハッシュの領域が壊れています".to_string()));
+        }
+        check_chain_count(&self.header, start_texts.len())?;
+        RainbowTable::from_sorted_texts(self.header, digest_len, self.endpoints, start_texts)
+    }
+}
+
+// レインボーテーブルを指定した形式で保存
+pub fn save_rainbow_table<P: AsRef<Path>>(
+    rainbow_table: &RainbowTable,
+    path: P,
+    format: TableFormat,
+) -> error::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_rainbow_table(rainbow_table, &mut file, format)?;
+    Ok(file.flush()?)
+}
+
+// レインボーテーブルを指定した形式で書き出す（標準出力への書き出しなど）
+pub fn write_rainbow_table<W: Write>(
+    rainbow_table: &RainbowTable,
+    mut writer: W,
+    format: TableFormat,
+) -> error::Result<()> {
+    match format {
+        TableFormat::Json => {
+            let json = JsonTableRef {
+                header: &rainbow_table.header,
+                table: HexChains(rainbow_table),
+            };
+            serde_json::to_writer(&mut writer, &json)?
+        }
+        TableFormat::Binary => {
+            writer.write_all(BINARY_MAGIC)?;
+            writer.write_all(&BINARY_VERSION.to_le_bytes())?;
+            let binary = BinaryTable::from_table(rainbow_table)?;
+            bincode::serialize_into(&mut writer, &binary)?;
+        }
+        TableFormat::Mapped => write_mapped(rainbow_table, &mut writer)?,
+        TableFormat::Compressed => write_compressed(rainbow_table, &mut writer)?,
+    }
+    Ok(writer.flush()?)
+}
+
+// レインボーテーブルをファイルからロード（形式は先頭のマジックナンバーで判別）
+// メモリマップ形式と圧縮形式のファイルは読み込まずにメモリマップし、照合に必要な部分だけをその都度読む
+pub fn load_rainbow_table<P: AsRef<Path>>(path: P) -> error::Result<RainbowTable> {
+    let mut reader = BufReader::new(File::open(path)?);
+    match TableFormat::from_magic(reader.fill_buf()?) {
+        format @ (TableFormat::Mapped | TableFormat::Compressed) => {
+            let rainbow_table = table_from_bytes(format, TableBytes::map(reader.get_ref())?)?;
+            rainbow_table.reducer()?;
+            Ok(rainbow_table)
+        }
+        _ => read_rainbow_table(reader),
+    }
+}
+
+// メモリマップ形式・圧縮形式のテーブルを、バイト列（メモリマップしたファイルなど）を展開せずに参照する
+fn table_from_bytes(format: TableFormat, bytes: TableBytes) -> error::Result<RainbowTable> {
+    Ok(if format == TableFormat::Mapped {
+        let (header, chains) = MappedChains::parse(bytes)?;
+        RainbowTable::from_mapped(header, chains)
+    } else {
+        let (header, chains) = CompressedChains::parse(bytes)?;
+        RainbowTable::from_compressed(header, chains)
+    })
+}
+
+// レインボーテーブルを読み込む（標準入力からの読み込みなど、形式は先頭のマジックナンバーで判別）
+// メモリマップ形式と圧縮形式はファイルと同じ配置のまま全体をメモリに読み込む
+pub fn read_rainbow_table<R: BufRead>(mut reader: R) -> error::Result<RainbowTable> {
+    let rainbow_table: RainbowTable = match TableFormat::from_magic(reader.fill_buf()?) {
+        format @ (TableFormat::Mapped | TableFormat::Compressed) => {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes)?;
+            table_from_bytes(format, TableBytes::Owned(bytes))?
+        }
+        TableFormat::Binary => {
+            let mut header = [0u8; 8];
+            reader.read_exact(&mut header)?;
+            let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            if version != BINARY_VERSION {
+                return Err(corrupt(format!(
+                    "未対応のバイナリ形式のバージョンです: {}",
+                    version
+                )));
+            }
+            bincode::deserialize_from::<_, BinaryTable>(&mut reader)?.into_table()?
+        }
+        TableFormat::Json => serde_json::from_reader::<_, JsonTable>(reader)?.into_table()?,
+    };
+    rainbow_table.reducer()?;
+    Ok(rainbow_table)
+}
+
+// レインボーテーブルをロードし、ヘッダが使用中のパラメータと異なる場合は拒否する
+pub fn load_rainbow_table_checked<P: AsRef<Path>>(
+    path: P,
+    expected: &TableParams,
+) -> error::Result<RainbowTable> {
+    let rainbow_table = load_rainbow_table(path)?;
+    rainbow_table.check_params(expected)?;
+    Ok(rainbow_table)
+}
+
+// テーブルファイルを別の形式に変換（旧形式のJSONからバイナリ形式への変換など）
+pub fn convert_rainbow_table<P, Q>(src: P, dst: Q, format: TableFormat) -> error::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let rainbow_table = load_rainbow_table(src)?;
+    save_rainbow_table(&rainbow_table, dst, format)
+}
+
+// ヘッダのチェーン数とファイル内のチェーン数が一致するか確認
+fn check_chain_count(header: &TableHeader, chain_count: usize) -> error::Result<()> {
+    if header.chain_count != chain_count as u64 {
+        return Err(corrupt(format!(
+            "ヘッダのチェーン数 {} と実際のチェーン数 {} が一致しません",
+            header.chain_count, chain_count
+        )));
+    }
+    Ok(())
+}
+
+fn corrupt(message: String) -> Error {
+    Error::CorruptTable(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crack::crack_hash;
+    use crate::generate::GenerateOptions;
+    use crate::hash::{HashAlgorithm, Sha1Hash};
+    use crate::starts::StartStorage;
+    use crate::test_util;
+
+    const FORMATS: [TableFormat; 4] = [
+        TableFormat::Json,
+        TableFormat::Binary,
+        TableFormat::Mapped,
+        TableFormat::Compressed,
+    ];
+
+    fn roundtrip(table: &RainbowTable, format: TableFormat) -> RainbowTable {
+        let mut bytes = Vec::new();
+        write_rainbow_table(table, &mut bytes, format).unwrap();
+        assert_eq!(TableFormat::from_magic(&bytes), format);
+        read_rainbow_table(&bytes[..]).unwrap()
+    }
+
+    fn assert_same(actual: &RainbowTable, expected: &RainbowTable) {
+        assert_eq!(actual.header, expected.header);
+        assert_eq!(actual.digest_len(), expected.digest_len());
+        assert_eq!(test_util::chains(actual), test_util::chains(expected));
+    }
+
+    // 各形式で書き出したテーブルを読み戻すと元と同じになること
+    // （user-007 バイナリ形式、user-008 ヘッダ、user-022 メモリマップ形式、user-023 圧縮形式）
+    #[test]
+    fn roundtrip_each_format() {
+        let table = test_util::table(300, GenerateOptions::default());
+        for format in FORMATS {
+            assert_same(&roundtrip(&table, format), &table);
+        }
+    }
+
+    // 開始点を番号で保存したテーブル（user-024）と終端ハッシュを切り詰めたテーブル（user-025）も各形式で読み戻せること
+    #[test]
+    fn roundtrip_indexed_and_truncated_tables() {
+        let indexed = test_util::table(
+            300,
+            GenerateOptions {
+                start_storage: StartStorage::Index,
+                ..Default::default()
+            },
+        );
+        assert_eq!(indexed.header.start_source, StartSource::Keyspace);
+        let truncated = test_util::table(
+            300,
+            GenerateOptions {
+                endpoint_len: Some(3),
+                collision_policy: CollisionPolicy::KeepAll,
+                ..Default::default()
+            },
+        );
+        assert_eq!(truncated.digest_len(), 3);
+        for table in [&indexed, &truncated] {
+            for format in FORMATS {
+                assert_same(&roundtrip(table, format), table);
+            }
+        }
+    }
+
+    // 終端ハッシュを切り詰めたテーブルでも、メモリ上（バイナリ形式から読み込み）・メモリマップ・圧縮の
+    // どの格納先でも、チェーンの各位置の平文を終端ハッシュでの探索だけで復元できること（user-025）
+    #[test]
+    fn cracks_truncated_tables() {
+        let chain_length = test_util::params().chain_length;
+        for endpoint_len in [1, 2, 4] {
+            let table = test_util::table(
+                200,
+                GenerateOptions {
+                    endpoint_len: Some(endpoint_len),
+                    collision_policy: CollisionPolicy::KeepAll,
+                    ..Default::default()
+                },
+            );
+            assert_eq!(table.digest_len(), endpoint_len);
+
+            let estimate = table.false_alarm_estimate().unwrap();
+            assert_eq!(estimate.endpoint_len, endpoint_len);
+            assert_eq!(estimate.digest_len, Sha1Hash.digest_len());
+            let expected = table.len() as f64 / 256f64.powi(endpoint_len as i32);
+            assert!((estimate.per_lookup - expected).abs() <= expected * 1e-9);
+            assert_eq!(estimate.per_hash, estimate.per_lookup * chain_length as f64);
+            assert_eq!(
+                estimate.extra_hashes,
+                estimate.per_hash * chain_length as f64
+            );
+
+            // 様々な位置の平文（チェーンの先頭と末尾を含む）
+            let reducer = table.reducer().unwrap();
+            let targets: Vec<(String, String)> = (0..table.len())
+                .step_by(11)
+                .map(|k| {
+                    let mut plaintext = table.start(k).into_owned();
+                    for j in 0..k * 7 % chain_length {
+                        reducer.reduce_into(
+                            &Sha1Hash.hash(plaintext.as_bytes()),
+                            j,
+                            &mut plaintext,
+                        );
+                    }
+                    (hex::encode(Sha1Hash.hash(plaintext.as_bytes())), plaintext)
+                })
+                .collect();
+
+            for format in [
+                TableFormat::Binary,
+                TableFormat::Mapped,
+                TableFormat::Compressed,
+            ] {
+                let loaded = roundtrip(&table, format);
+                for (target_hash, plaintext) in &targets {
+                    assert_eq!(
+                        crack_hash(&loaded, &Sha1Hash, target_hash)
+                            .unwrap()
+                            .as_ref(),
+                        Some(plaintext),
+                        "{:?} {}",
+                        format,
+                        endpoint_len
+                    );
+                }
+            }
+        }
+    }
+
+    // チェーンのないテーブルも各形式で読み戻せること（user-007、user-022、user-023）
+    #[test]
+    fn roundtrip_empty_table() {
+        let table = test_util::table(0, GenerateOptions::default());
+        for format in FORMATS {
+            assert!(roundtrip(&table, format).is_empty());
+        }
+    }
+
+    // 未対応のバージョンのバイナリ形式は壊れたファイルとして扱う（user-007）
+    #[test]
+    fn rejects_unknown_binary_version() {
+        let table = test_util::table(10, GenerateOptions::default());
+        let mut bytes = Vec::new();
+        write_rainbow_table(&table, &mut bytes, TableFormat::Binary).unwrap();
+        bytes[4..8].copy_from_slice(&99u32.to_le_bytes());
+        assert!(matches!(
+            read_rainbow_table(&bytes[..]),
+            Err(Error::CorruptTable(_))
+        ));
+    }
+
+    // 読み込み時は並べ替えないので、昇順でない終端ハッシュは壊れたファイルとして扱う（user-025）
+    #[test]
+    fn rejects_unsorted_binary() {
+        let table = test_util::table(10, GenerateOptions::default());
+        let mut binary = BinaryTable::from_table(&table).unwrap();
+        let digest_len = binary.digest_len as usize;
+        let (first, rest) = binary.endpoints.split_at_mut(digest_len);
+        first.swap_with_slice(&mut rest[..digest_len]);
+        assert!(matches!(binary.into_table(), Err(Error::CorruptTable(_))));
+    }
+
+    // ヘッダのない初期実装のJSON（rainbow_table.json の先頭の数チェーン）を読み込めること（user-008）
+    #[test]
+    fn reads_headerless_legacy_json() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/legacy_table.json"
+        );
+        let table = load_rainbow_table(path).unwrap();
+        assert_eq!(*table.params(), TableParams::legacy());
+        assert_eq!(table.header.chain_count, 4);
+        assert_eq!(table.len(), 4);
+
+        // 初期実装のパラメータで開始点からたどると、保存されている終端ハッシュになる
+        let reducer = table.reducer().unwrap();
+        let mut end_hash = vec![0; Sha1Hash.digest_len()];
+        for (stored, start_text) in table.iter() {
+            crate::generate::chain_end(
+                &Sha1Hash,
+                &reducer,
+                table.params().chain_length,
+                &start_text,
+                &mut end_hash,
+            );
+            assert_eq!(*stored, end_hash[..]);
+        }
+    }
+}
@@ -0,0 +1,544 @@
+use crate::checkpoint::{Checkpointer, Progress};
+use crate::error::{Error, Result};
+use crate::hash::HashAlgorithm;
+use crate::progress::{count_lines, ProgressReporter, ProgressStyle};
+use crate::reduce::Reducer;
+use crate::starts::{StartStorage, Wordlist};
+use crate::table::{CollisionPolicy, CollisionReport, RainbowTable, TableHeader, TableParams};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// 一度にまとめて計算する開始点の数（スレッド数によらず一定にして結果を再現可能にする）
+const BATCH_SIZE: usize = 1 << 14;
+
+// テーブル生成のオプション（生成結果のチェーンの計算には影響しない）
+#[derive(Clone, Debug, Default)]
+pub struct GenerateOptions {
+    // 終端ハッシュが重複したチェーンの扱い（perfect の場合は無視される）
+    pub collision_policy: CollisionPolicy,
+    // 終端ハッシュごとにチェーンを1本だけ残す完全テーブルを生成する
+    pub perfect: bool,
+    // 完全テーブルの一意なチェーンがこの数に満たない場合、キースペースの先頭から順に開始点を追加する
+    pub target_chains: Option<usize>,
+    // チェーンを計算するスレッド数（0 の場合は利用可能なCPU数）
+    pub threads: usize,
+    // 途中経過を保存するチェックポイントファイル（既にある場合はその続きから生成する）
+    // 同じ入力とパラメータで再開すれば、中断しなかった場合と同じテーブルになる
+    pub checkpoint: Option<PathBuf>,
+    // チェックポイントを書き出す間隔（0 の場合はバッチごと）
+    pub checkpoint_interval: Duration,
+    // 中断の要求（立つと計算中のバッチを終えてから、それまでのチェーンで部分的なテーブルを返す）
+    pub cancel: Option<Arc<AtomicBool>>,
+    // 進捗表示の方法
+    pub progress: ProgressStyle,
+    // パスワードリストの行数（残り時間の見積もりに使う、ファイルから読む場合は省略すると数える）
+    pub total_lines: Option<u64>,
+    // 開始点の保存方法（番号の場合、ファイルから読むときは行番号、読み込み元から読むときはキースペースの通し番号）
+    pub start_storage: StartStorage,
+    // 終端ハッシュのうち保存する先頭のバイト数（None の場合はすべて保存する）
+    // 短くするとテーブルは小さくなるが、照合時の誤警報が増える
+    pub endpoint_len: Option<usize>,
+}
+
+// スレッド数の指定を解決（0 の場合は利用可能なCPU数）
+pub(crate) fn worker_count(threads: usize) -> usize {
+    match threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+}
+
+// 中断が要求されたか
+pub(crate) fn cancelled(cancel: &Option<Arc<AtomicBool>>) -> bool {
+    cancel
+        .as_ref()
+        .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+}
+
+// 開始プレインテキストからチェーンをたどり、終端のハッシュ値を out に書き込む
+pub(crate) fn chain_end<H: HashAlgorithm + ?Sized>(
+    hasher: &H,
+    reducer: &Reducer,
+    chain_length: usize,
+    start_text: &str,
+    out: &mut [u8],
+) {
+    let mut plaintext = start_text.to_string();
+    for j in 0..chain_length {
+        hasher.hash_into(plaintext.as_bytes(), out);
+        reducer.reduce_into(out, j, &mut plaintext);
+    }
+    hasher.hash_into(plaintext.as_bytes(), out);
+}
+
+// 複数の開始点のチェーンを複数スレッドで計算する
+struct ChainComputer<'a, H: ?Sized> {
+    hasher: &'a H,
+    reducer: &'a Reducer,
+    chain_length: usize,
+    threads: usize,
+}
+
+impl<H: HashAlgorithm + ?Sized> ChainComputer<'_, H> {
+    // 開始点の数を単位とする進捗表示
+    fn reporter(&self, options: &GenerateOptions, total: Option<u64>) -> ProgressReporter {
+        ProgressReporter::new(options.progress, "生成", "チェーン", total)
+            .with_hashes_per_unit(self.chain_length as u64 + 1)
+    }
+
+    // 各開始点の終端ハッシュを開始点と同じ順序で返す
+    fn chain_ends(&self, starts: &[String]) -> Vec<Vec<u8>> {
+        let mut ends = vec![vec![0; self.hasher.digest_len()]; starts.len()];
+        let per_thread = starts.len().div_ceil(self.threads).max(1);
+        thread::scope(|scope| {
+            for (starts, ends) in starts.chunks(per_thread).zip(ends.chunks_mut(per_thread)) {
+                scope.spawn(move || {
+                    for (start_text, end_hash) in starts.iter().zip(ends) {
+                        chain_end(
+                            self.hasher,
+                            self.reducer,
+                            self.chain_length,
+                            start_text,
+                            end_hash,
+                        );
+                    }
+                });
+            }
+        });
+        ends
+    }
+}
+
+// 次の開始点を最大 count 個読み込む
+fn read_batch<R: BufRead>(lines: &mut io::Lines<R>, count: usize) -> io::Result<Vec<String>> {
+    lines.take(count).collect()
+}
+
+// ファイルからパスワードリストを読み込み、レインボーテーブルを生成
+// 終端ハッシュの重複の集計もあわせて返す
+pub fn generate_rainbow_table<H, P>(
+    hasher: &H,
+    params: &TableParams,
+    wordlist: P,
+    options: &GenerateOptions,
+) -> Result<(RainbowTable, CollisionReport)>
+where
+    H: HashAlgorithm + ?Sized,
+    P: AsRef<Path>,
+{
+    let mut options = options.clone();
+    if options.total_lines.is_none() && options.progress != ProgressStyle::Silent {
+        options.total_lines = Some(count_lines(&wordlist)?);
+    }
+    // 行番号はパスワードリスト全体のSHA-1とあわせて記録する
+    let source = match options.start_storage {
+        StartStorage::Index => Some(Arc::new(Wordlist::open(&wordlist)?)),
+        StartStorage::Plaintext => None,
+    };
+    let file = File::open(wordlist)?;
+    let (table, report) = generate_table(hasher, params, io::BufReader::new(file), &options)?;
+    Ok((finish_table(table, &options, source)?, report))
+}
+
+// パスワードリスト（1行に1つ）を読み込み元から読み込み、レインボーテーブルを生成
+// 標準入力など、ファイル以外からパスワードリストを渡す場合に使う
+pub fn generate_rainbow_table_from_reader<H, R>(
+    hasher: &H,
+    params: &TableParams,
+    wordlist: R,
+    options: &GenerateOptions,
+) -> Result<(RainbowTable, CollisionReport)>
+where
+    H: HashAlgorithm + ?Sized,
+    R: BufRead,
+{
+    let (table, report) = generate_table(hasher, params, wordlist, options)?;
+    Ok((finish_table(table, options, None)?, report))
+}
+
+// 生成したテーブルを開始点の保存方法と終端ハッシュの長さの指定に合わせる
+// 開始点を番号にする場合、source を指定するとその行番号、指定しない場合はキースペースの通し番号にする
+fn finish_table(
+    mut table: RainbowTable,
+    options: &GenerateOptions,
+    source: Option<Arc<Wordlist>>,
+) -> Result<RainbowTable> {
+    if options.start_storage == StartStorage::Index {
+        table = table.with_start_storage(StartStorage::Index, source)?;
+    }
+    if let Some(endpoint_len) = options.endpoint_len {
+        table = table.with_endpoint_len(endpoint_len)?;
+    }
+    Ok(table)
+}
+
+// 開始プレインテキストを保存したテーブルを生成
+fn generate_table<H, R>(
+    hasher: &H,
+    params: &TableParams,
+    wordlist: R,
+    options: &GenerateOptions,
+) -> Result<(RainbowTable, CollisionReport)>
+where
+    H: HashAlgorithm + ?Sized,
+    R: BufRead,
+{
+    if hasher.name() != params.algorithm {
+        return Err(Error::TableMismatch(format!(
+            "パラメータのハッシュアルゴリズム {} と {} が一致しません",
+            params.algorithm,
+            hasher.name()
+        )));
+    }
+    let reducer = params.reducer()?;
+    let computer = ChainComputer {
+        hasher,
+        reducer: &reducer,
+        chain_length: params.chain_length,
+        threads: worker_count(options.threads),
+    };
+    let mut checkpointer = Checkpointer::new(params, options);
+    let mut lines = wordlist.lines();
+    if options.perfect {
+        return generate_perfect_table(&computer, params, lines, options, checkpointer);
+    }
+
+    let mut reporter = computer.reporter(options, options.total_lines);
+    let mut progress = resume(&checkpointer, &mut lines, &reporter)?;
+    reporter.resume_from(progress.wordlist_lines);
+    let mut partial = false;
+    loop {
+        if cancelled(&options.cancel) {
+            checkpointer.save(&mut progress)?;
+            partial = true;
+            break;
+        }
+        let starts = read_batch(&mut lines, BATCH_SIZE)?;
+        if starts.is_empty() {
+            break;
+        }
+        progress.wordlist_lines += starts.len() as u64;
+        progress.wordlist_digest.update(&starts);
+        let ends = computer.chain_ends(&starts);
+        progress.chains.extend(ends.into_iter().zip(starts));
+
+        reporter.update(progress.wordlist_lines);
+        checkpointer.update(&mut progress)?;
+    }
+    reporter.finish();
+
+    let mut header = TableHeader::new(params.clone(), 0);
+    header.partial = partial;
+    RainbowTable::from_chains(
+        header,
+        hasher.digest_len(),
+        progress.chains,
+        options.collision_policy,
+    )
+}
+
+// チェックポイントがあれば途中経過を読み込み、それまでに読み込んだ行を読み飛ばす
+// 読み飛ばした行がチェックポイントを保存したときと異なる場合はエラー
+fn resume<R: BufRead>(
+    checkpointer: &Checkpointer,
+    lines: &mut io::Lines<R>,
+    reporter: &ProgressReporter,
+) -> Result<Progress> {
+    let Some(mut progress) = checkpointer.resume()? else {
+        return Ok(Progress::default());
+    };
+    for _ in 0..progress.wordlist_lines {
+        let Some(line) = lines.next().transpose()? else {
+            return Err(Error::TableMismatch(
+                "パスワードリストがチェックポイントの記録より短くなっています".to_string(),
+            ));
+        };
+        progress.wordlist_digest.update(&[line]);
+    }
+    Checkpointer::verify_wordlist(&progress)?;
+    reporter.message(&format!(
+        "チェックポイントから再開します（チェーン {} 本、リスト {} 行目まで計算済み）",
+        progress.chains.len(),
+        progress.wordlist_lines
+    ));
+    Ok(progress)
+}
+
+// 完全テーブルを生成
+// 開始点が重複するチェーンは計算せず、終端ハッシュが既出のチェーンは捨てる
+fn generate_perfect_table<H, R>(
+    computer: &ChainComputer<H>,
+    params: &TableParams,
+    mut lines: io::Lines<R>,
+    options: &GenerateOptions,
+    mut checkpointer: Checkpointer,
+) -> Result<(RainbowTable, CollisionReport)>
+where
+    H: HashAlgorithm + ?Sized,
+    R: BufRead,
+{
+    // 目標のチェーン数に達するまで、リストの後にキースペースから開始点を追加
+    let target_chains = options.target_chains.unwrap_or(0);
+    let total = options
+        .total_lines
+        .map(|total_lines| total_lines.max(target_chains as u64));
+    let mut reporter = computer.reporter(options, total);
+    // リストを読み終えていても、同じリストか確かめるため読み込み済みの行をすべて読み飛ばす
+    let mut progress = resume(&checkpointer, &mut lines, &reporter)?;
+    let mut seen_starts: HashSet<String> = progress
+        .chains
+        .iter()
+        .map(|(_, start_text)| start_text)
+        .chain(&progress.merged_starts)
+        .cloned()
+        .collect();
+    let mut seen_ends: HashSet<Vec<u8>> = progress
+        .chains
+        .iter()
+        .map(|(end_hash, _)| end_hash.clone())
+        .collect();
+    reporter.resume_from(progress.report.chains as u64);
+    let mut partial = false;
+    loop {
+        if cancelled(&options.cancel) {
+            checkpointer.save(&mut progress)?;
+            partial = true;
+            break;
+        }
+        let mut starts = Vec::new();
+        if !progress.wordlist_done {
+            starts = read_batch(&mut lines, BATCH_SIZE)?;
+            progress.wordlist_lines += starts.len() as u64;
+            progress.wordlist_digest.update(&starts);
+            progress.wordlist_done = starts.is_empty();
+        }
+        if progress.wordlist_done {
+            // 不足分だけ追加するので、どの開始点まで使うかはスレッド数によらない
+            let count = target_chains
+                .saturating_sub(progress.chains.len())
+                .min(BATCH_SIZE);
+            while starts.len() < count {
+                match params.keyspace.nth(progress.keyspace_index) {
+                    Some(start_text) => starts.push(start_text),
+                    None => break,
+                }
+                progress.keyspace_index += 1;
+            }
+            if starts.is_empty() {
+                break;
+            }
+            // キースペースから追加する開始点の数は、重複や衝突の数によって決まる
+            let remaining = target_chains.saturating_sub(progress.chains.len());
+            reporter.set_total(Some((progress.report.chains + remaining) as u64));
+        }
+
+        let report = &mut progress.report;
+        report.chains += starts.len();
+        starts.retain(|start_text| seen_starts.insert(start_text.clone()));
+        report.duplicate_starts = report.chains - seen_starts.len();
+
+        let ends = computer.chain_ends(&starts);
+        for (end_hash, start_text) in ends.into_iter().zip(starts) {
+            if seen_ends.insert(end_hash.clone()) {
+                progress.chains.push((end_hash, start_text));
+            } else {
+                report.merged_chains += 1;
+                progress.merged_starts.push(start_text);
+            }
+        }
+
+        reporter.update(report.chains as u64);
+        checkpointer.update(&mut progress)?;
+    }
+    reporter.finish();
+
+    let mut report = progress.report;
+    let mut header = TableHeader::new(params.clone(), 0);
+    header.partial = partial;
+    let (table, _) = RainbowTable::from_chains(
+        header,
+        computer.hasher.digest_len(),
+        progress.chains,
+        CollisionPolicy::KeepFirst,
+    )?;
+    report.stored = table.len();
+    Ok((table, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::Sha1Hash;
+    use crate::test_util;
+    use std::io::Read;
+
+    // 先頭から at バイト目までを読み終えた時点で中断を要求する読み込み元
+    struct CancelAfter<'a> {
+        data: &'a [u8],
+        pos: usize,
+        at: usize,
+        cancel: Arc<AtomicBool>,
+    }
+
+    impl Read for CancelAfter<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = (&self.data[self.pos..]).read(buf)?;
+            self.consume(n);
+            Ok(n)
+        }
+    }
+
+    impl BufRead for CancelAfter<'_> {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            Ok(&self.data[self.pos..])
+        }
+
+        fn consume(&mut self, amount: usize) {
+            self.pos += amount;
+            if self.pos >= self.at {
+                self.cancel.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // バッチを複数回読むため、チェーンを短くして計算量を抑える
+    fn params() -> TableParams {
+        TableParams {
+            chain_length: 4,
+            ..test_util::params()
+        }
+    }
+
+    fn checkpoint_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rsa-{}-{}.ckpt", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    // 最初のバッチの後で中断し、チェックポイントから再開したテーブルが中断しなかった場合と同じになること
+    fn assert_resume_matches(name: &str, options: GenerateOptions) {
+        let params = params();
+        let wordlist = test_util::wordlist(BATCH_SIZE * 2 + 100);
+        let options = GenerateOptions {
+            progress: ProgressStyle::Silent,
+            checkpoint: Some(checkpoint_path(name)),
+            checkpoint_interval: Duration::ZERO,
+            ..options
+        };
+
+        let uninterrupted = GenerateOptions {
+            checkpoint: None,
+            ..options.clone()
+        };
+        let (expected, expected_report) = generate_rainbow_table_from_reader(
+            &Sha1Hash,
+            &params,
+            wordlist.as_bytes(),
+            &uninterrupted,
+        )
+        .unwrap();
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let first_batch_end = wordlist.match_indices('\n').nth(BATCH_SIZE - 1).unwrap().0 + 1;
+        let reader = CancelAfter {
+            data: wordlist.as_bytes(),
+            pos: 0,
+            at: first_batch_end,
+            cancel: cancel.clone(),
+        };
+        let interrupted_options = GenerateOptions {
+            cancel: Some(cancel),
+            ..options.clone()
+        };
+        let (partial, _) =
+            generate_rainbow_table_from_reader(&Sha1Hash, &params, reader, &interrupted_options)
+                .unwrap();
+        assert!(partial.header.partial);
+        assert!(partial.len() < expected.len());
+
+        let (resumed, report) =
+            generate_rainbow_table_from_reader(&Sha1Hash, &params, wordlist.as_bytes(), &options)
+                .unwrap();
+        std::fs::remove_file(options.checkpoint.unwrap()).unwrap();
+        assert!(!resumed.header.partial);
+        assert_eq!(resumed.header.params, expected.header.params);
+        assert_eq!(test_util::chains(&resumed), test_util::chains(&expected));
+        assert_eq!(report, expected_report);
+    }
+
+    #[test]
+    fn resume_matches_uninterrupted_run() {
+        assert_resume_matches("resume", GenerateOptions::default());
+    }
+
+    #[test]
+    fn resume_matches_uninterrupted_perfect_run() {
+        assert_resume_matches(
+            "resume-perfect",
+            GenerateOptions {
+                perfect: true,
+                target_chains: Some(BATCH_SIZE * 3),
+                ..Default::default()
+            },
+        );
+    }
+
+    // スレッド数によらず同じテーブルになること（複数のバッチにまたがる行数で確かめる）
+    #[test]
+    fn same_table_for_any_thread_count() {
+        let params = params();
+        let wordlist = test_util::wordlist(BATCH_SIZE * 2 + 100);
+        let generate = |threads| {
+            let options = GenerateOptions {
+                threads,
+                progress: ProgressStyle::Silent,
+                ..Default::default()
+            };
+            generate_rainbow_table_from_reader(&Sha1Hash, &params, wordlist.as_bytes(), &options)
+                .unwrap()
+        };
+        let (single, single_report) = generate(1);
+        let (multi, multi_report) = generate(4);
+        assert_eq!(test_util::chains(&multi), test_util::chains(&single));
+        assert_eq!(multi_report, single_report);
+    }
+
+    #[test]
+    fn resume_rejects_different_wordlist() {
+        let params = params();
+        let path = checkpoint_path("resume-mismatch");
+        let cancel = Arc::new(AtomicBool::new(false));
+        let wordlist = test_util::wordlist(BATCH_SIZE + 10);
+        let options = GenerateOptions {
+            progress: ProgressStyle::Silent,
+            checkpoint: Some(path.clone()),
+            checkpoint_interval: Duration::ZERO,
+            cancel: Some(cancel.clone()),
+            ..Default::default()
+        };
+        let reader = CancelAfter {
+            data: wordlist.as_bytes(),
+            pos: 0,
+            at: 0,
+            cancel,
+        };
+        generate_rainbow_table_from_reader(&Sha1Hash, &params, reader, &options).unwrap();
+
+        // 同じ行数で先頭の行だけが異なるリスト
+        let edited = format!("zzzzzz{}", &wordlist[wordlist.find('\n').unwrap()..]);
+        let options = GenerateOptions {
+            cancel: None,
+            ..options
+        };
+        let result =
+            generate_rainbow_table_from_reader(&Sha1Hash, &params, edited.as_bytes(), &options);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(Error::TableMismatch(_))));
+    }
+}
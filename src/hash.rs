@@ -0,0 +1,98 @@
+use md4::Md4;
+use sha1::{Digest, Sha1};
+
+// ハッシュアルゴリズムの抽象化
+// 名前はテーブルに保存され、別のアルゴリズムのテーブルを誤って使わないための照合に使う
+pub trait HashAlgorithm: Send + Sync {
+    // テーブルに記録するアルゴリズム名
+    fn name(&self) -> &'static str;
+
+    // ダイジェストのバイト長
+    fn digest_len(&self) -> usize;
+
+    // input のハッシュ値を out（長さ digest_len）に書き込む
+    fn hash_into(&self, input: &[u8], out: &mut [u8]);
+
+    // ハッシュ値を新しいバッファに計算
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        let mut out = vec![0; self.digest_len()];
+        self.hash_into(input, &mut out);
+        out
+    }
+}
+
+// ソルトなしSHA-1
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha1Hash;
+
+impl HashAlgorithm for Sha1Hash {
+    fn name(&self) -> &'static str {
+        "sha1"
+    }
+
+    fn digest_len(&self) -> usize {
+        20
+    }
+
+    fn hash_into(&self, input: &[u8], out: &mut [u8]) {
+        out.copy_from_slice(&Sha1::digest(input));
+    }
+}
+
+// Windows NTLMハッシュ（UTF-16LEに変換した平文のMD4）
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ntlm;
+
+impl HashAlgorithm for Ntlm {
+    fn name(&self) -> &'static str {
+        "ntlm"
+    }
+
+    fn digest_len(&self) -> usize {
+        16
+    }
+
+    fn hash_into(&self, input: &[u8], out: &mut [u8]) {
+        let mut hasher = Md4::new();
+        for unit in String::from_utf8_lossy(input).encode_utf16() {
+            hasher.update(unit.to_le_bytes());
+        }
+        out.copy_from_slice(&hasher.finalize());
+    }
+}
+
+// 名前から組み込みのハッシュアルゴリズムを取得
+pub fn algorithm_by_name(name: &str) -> Option<Box<dyn HashAlgorithm>> {
+    match name {
+        "sha1" => Some(Box::new(Sha1Hash)),
+        "ntlm" => Some(Box::new(Ntlm)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ntlm(input: &[u8]) -> String {
+        hex::encode(Ntlm.hash(input))
+    }
+
+    #[test]
+    fn ntlm_known_answers() {
+        assert_eq!(ntlm(b""), "31d6cfe0d16ae931b73c59d7e0c089c0");
+        assert_eq!(ntlm(b"password"), "8846f7eaee8fb117ad06bdd830b7586c");
+        // 非ASCII文字は UTF-16LE に変換してからハッシュ化する（サロゲートペアを含む）
+        assert_eq!(
+            ntlm("pässwörd".as_bytes()),
+            "0553152250ac01adb4213cb9938663e4"
+        );
+        assert_eq!(
+            ntlm("パスワード".as_bytes()),
+            "62d6a9aa1ea010222c5e9fc49563d6a8"
+        );
+        assert_eq!(ntlm("😀".as_bytes()), "4b58a10cc20a4e7d808d218e1f80aabc");
+        // UTF-8 として不正なバイト列は置換文字 U+FFFD として扱う
+        assert_eq!(ntlm(b"\xff"), "48498df91e4c1700370a09c6c51a055f");
+    }
+}
@@ -0,0 +1,225 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~ ";
+
+// リダクション関数が出力する平文の空間
+// 文字種と長さの範囲、必要なら位置ごとの文字種を指定する（文字はASCIIのみ）
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "KeyspaceSpec", into = "KeyspaceSpec")]
+pub struct Keyspace {
+    charset: String,
+    min_length: usize,
+    max_length: usize,
+    position_charsets: Vec<String>,
+}
+
+// シリアライズ用の検証前の表現
+#[derive(Clone, Serialize, Deserialize)]
+struct KeyspaceSpec {
+    charset: String,
+    min_length: usize,
+    max_length: usize,
+    #[serde(default)]
+    position_charsets: Vec<String>,
+}
+
+impl Keyspace {
+    // 全位置で同じ文字種を使うキースペースを作成
+    pub fn new(charset: &str, min_length: usize, max_length: usize) -> Result<Self> {
+        validate_charset(charset)?;
+        if min_length == 0 || min_length > max_length {
+            return Err(invalid(format!(
+                "長さの範囲が不正です: {}..={}",
+                min_length, max_length
+            )));
+        }
+        Ok(Keyspace {
+            charset: charset.to_string(),
+            min_length,
+            max_length,
+            position_charsets: Vec::new(),
+        })
+    }
+
+    // 先頭から順に位置ごとの文字種を指定する（指定のない位置は共通の文字種を使う）
+    pub fn with_position_charsets(mut self, charsets: Vec<String>) -> Result<Self> {
+        if charsets.len() > self.max_length {
+            return Err(invalid(format!(
+                "位置ごとの文字種が最大長 {} を超えています",
+                self.max_length
+            )));
+        }
+        for charset in &charsets {
+            validate_charset(charset)?;
+        }
+        self.position_charsets = charsets;
+        Ok(self)
+    }
+
+    // 従来のreduceと同じ英数字6〜8文字
+    pub fn legacy() -> Self {
+        Keyspace::alphanumeric(6, 8).expect("既定のキースペースは有効")
+    }
+
+    // 英小文字のみ
+    pub fn lowercase(min_length: usize, max_length: usize) -> Result<Self> {
+        Keyspace::new(LOWERCASE, min_length, max_length)
+    }
+
+    // 英大文字・英小文字・数字
+    pub fn alphanumeric(min_length: usize, max_length: usize) -> Result<Self> {
+        Keyspace::new(
+            &format!("{}{}{}", UPPERCASE, LOWERCASE, DIGITS),
+            min_length,
+            max_length,
+        )
+    }
+
+    // 空白を含む印字可能なASCII文字すべて
+    pub fn printable(min_length: usize, max_length: usize) -> Result<Self> {
+        Keyspace::new(
+            &format!("{}{}{}{}", UPPERCASE, LOWERCASE, DIGITS, SYMBOLS),
+            min_length,
+            max_length,
+        )
+    }
+
+    pub fn charset(&self) -> &str {
+        &self.charset
+    }
+
+    pub fn min_length(&self) -> usize {
+        self.min_length
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    pub fn position_charsets(&self) -> &[String] {
+        &self.position_charsets
+    }
+
+    // position 文字目に使う文字種
+    pub fn charset_at(&self, position: usize) -> &[u8] {
+        self.position_charsets
+            .get(position)
+            .unwrap_or(&self.charset)
+            .as_bytes()
+    }
+
+    // 長さ length の平文の個数（u128に収まらない場合は None）
+    pub fn count_of_length(&self, length: usize) -> Option<u128> {
+        (0..length).try_fold(1u128, |count, i| {
+            count.checked_mul(self.charset_at(i).len() as u128)
+        })
+    }
+
+    // キースペース全体の平文の個数（u128に収まらない場合は None）
+    pub fn size(&self) -> Option<u128> {
+        (self.min_length..=self.max_length).try_fold(0u128, |total, length| {
+            total.checked_add(self.count_of_length(length)?)
+        })
+    }
+
+    // 短い順・各長さの中では先頭の文字ほど速く変わる順に数えた index 番目の平文
+    // （index がキースペースの大きさ以上の場合は None）
+    pub fn nth(&self, mut index: u128) -> Option<String> {
+        let mut length = self.min_length;
+        loop {
+            if length > self.max_length {
+                return None;
+            }
+            match self.count_of_length(length) {
+                Some(count) if index >= count => index -= count,
+                _ => break,
+            }
+            length += 1;
+        }
+        let mut plaintext = String::with_capacity(length);
+        self.write_plaintext(index, length, &mut plaintext);
+        Some(plaintext)
+    }
+
+    // nth の逆変換（キースペースに含まれない平文の場合は None）
+    pub fn index_of(&self, plaintext: &str) -> Option<u128> {
+        if !self.contains(plaintext) {
+            return None;
+        }
+        let bytes = plaintext.as_bytes();
+        let mut index = (self.min_length..bytes.len()).try_fold(0u128, |total, length| {
+            total.checked_add(self.count_of_length(length)?)
+        })?;
+        let mut place = 1u128;
+        for (i, c) in bytes.iter().enumerate() {
+            let charset = self.charset_at(i);
+            let digit = charset.iter().position(|b| b == c)? as u128;
+            index = index.checked_add(digit.checked_mul(place)?)?;
+            place = place.saturating_mul(charset.len() as u128);
+        }
+        Some(index)
+    }
+
+    // 長さ length の平文のうち index 番目を out に追加する
+    pub(crate) fn write_plaintext(&self, mut index: u128, length: usize, out: &mut String) {
+        for i in 0..length {
+            let charset = self.charset_at(i);
+            let charset_len = charset.len() as u128;
+            out.push(charset[(index % charset_len) as usize] as char);
+            index /= charset_len;
+        }
+    }
+
+    // 平文がこのキースペースに含まれるか
+    pub fn contains(&self, plaintext: &str) -> bool {
+        let bytes = plaintext.as_bytes();
+        (self.min_length..=self.max_length).contains(&bytes.len())
+            && bytes
+                .iter()
+                .enumerate()
+                .all(|(i, c)| self.charset_at(i).contains(c))
+    }
+}
+
+impl TryFrom<KeyspaceSpec> for Keyspace {
+    type Error = Error;
+
+    fn try_from(spec: KeyspaceSpec) -> Result<Self> {
+        Keyspace::new(&spec.charset, spec.min_length, spec.max_length)?
+            .with_position_charsets(spec.position_charsets)
+    }
+}
+
+impl From<Keyspace> for KeyspaceSpec {
+    fn from(keyspace: Keyspace) -> Self {
+        KeyspaceSpec {
+            charset: keyspace.charset,
+            min_length: keyspace.min_length,
+            max_length: keyspace.max_length,
+            position_charsets: keyspace.position_charsets,
+        }
+    }
+}
+
+// 文字種は空でなく、重複のないASCII文字であること
+fn validate_charset(charset: &str) -> Result<()> {
+    if charset.is_empty() || !charset.is_ascii() {
+        return Err(invalid(format!(
+            "文字種は1文字以上のASCII文字で指定してください: {:?}",
+            charset
+        )));
+    }
+    let bytes = charset.as_bytes();
+    if (1..bytes.len()).any(|i| bytes[..i].contains(&bytes[i])) {
+        return Err(invalid(format!("文字種に重複があります: {:?}", charset)));
+    }
+    Ok(())
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidParameter(message)
+}
@@ -0,0 +1,38 @@
+pub mod batch;
+mod checkpoint;
+mod compressed;
+pub mod crack;
+pub mod error;
+pub mod format;
+pub mod generate;
+pub mod hash;
+pub mod keyspace;
+mod mapped;
+pub mod progress;
+pub mod reduce;
+mod sort_merge;
+pub mod starts;
+pub mod table;
+#[cfg(test)]
+mod test_util;
+
+pub use batch::{
+    crack_hash_file, crack_hash_list, crack_hash_reader, BatchEngine, BatchOptions, BatchStatus,
+    BatchSummary,
+};
+pub use crack::{crack_hash, crack_hash_in_tables, crack_hash_with_threads};
+pub use error::{Error, Result};
+pub use format::{
+    convert_rainbow_table, load_rainbow_table, load_rainbow_table_checked, read_rainbow_table,
+    save_rainbow_table, write_rainbow_table, TableFormat,
+};
+pub use generate::{generate_rainbow_table, generate_rainbow_table_from_reader, GenerateOptions};
+pub use hash::{algorithm_by_name, HashAlgorithm, Ntlm, Sha1Hash};
+pub use keyspace::Keyspace;
+pub use progress::{count_lines, ProgressStyle};
+pub use reduce::{Reducer, Reduction};
+pub use starts::{StartSource, StartStorage, Wordlist, WordlistFingerprint};
+pub use table::{
+    CollisionPolicy, CollisionReport, FalseAlarmEstimate, RainbowTable, TableHeader, TableParams,
+    CHAIN_LENGTH,
+};
@@ -1,140 +1,679 @@
-use serde::{Deserialize, Serialize};
-use sha1::{Digest, Sha1};
-use std::collections::HashMap;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rsa::{
+    algorithm_by_name, count_lines, crack_hash_in_tables, crack_hash_reader,
+    crack_hash_with_threads, generate_rainbow_table, generate_rainbow_table_from_reader,
+    load_rainbow_table, read_rainbow_table, save_rainbow_table, write_rainbow_table, BatchEngine,
+    BatchOptions, CollisionPolicy, Error, GenerateOptions, HashAlgorithm, Keyspace, ProgressStyle,
+    RainbowTable, Reduction, Result, Sha1Hash, StartSource, StartStorage, TableFormat, TableParams,
+    Wordlist, CHAIN_LENGTH,
+};
 use std::fs::{self, File};
-use std::io::{self, BufRead, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
-// レインボーチェーンの長さ
-const CHAIN_LENGTH: usize = 300;
 const RAINBOW_TABLE_FILE: &str = "rainbow_table.json";
+const WORDLIST_FILE: &str = "list.txt";
 
-// レインボーテーブルの型定義（シリアライズ用）
-#[derive(Serialize, Deserialize)]
-struct RainbowTable {
-    // 16進数文字列のハッシュ値をキーとし、プレインテキストを値とする構造に変更
-    table: HashMap<String, String>,
+// 標準入力・標準出力を表すパス
+const STDIO_PATH: &str = "-";
+
+// 終了コード（0: 成功、1: 復元できなかったハッシュ値がある、2: 引数の誤り、3: 実行時のエラー、
+// 130: SIGINT/SIGTERM で中断した）
+const EXIT_NOT_CRACKED: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_ERROR: u8 = 3;
+const EXIT_INTERRUPTED: u8 = 130;
+
+/// レインボーテーブルの生成と照合
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-// SHA-1ハッシュを計算
-fn hash(input: &str) -> Vec<u8> {
-    let mut hasher = Sha1::new();
-    hasher.update(input.as_bytes());
-    hasher.finalize().to_vec()
+#[derive(Subcommand)]
+enum Command {
+    /// パスワードリストからレインボーテーブルを生成する
+    Generate(GenerateArgs),
+    /// ハッシュ値をレインボーテーブルで照合して平文を復元する
+    Crack(CrackArgs),
+    /// テーブルのヘッダと統計を表示する
+    Info(InfoArgs),
+    /// テーブルファイルの形式を変換する
+    Convert(ConvertArgs),
 }
 
-fn reduce(hash: &[u8], position: usize) -> String {
-    let mut num = u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]) ^ (position as u32);
-    num = num.wrapping_add(u32::from_be_bytes([hash[4], hash[5], hash[6], hash[7]]));
+#[derive(Args)]
+struct GenerateArgs {
+    /// 開始点のパスワードリスト（- の場合は標準入力）
+    #[arg(short, long, default_value = WORDLIST_FILE)]
+    wordlist: PathBuf,
+    /// 出力するテーブルファイル（- の場合は標準出力）
+    #[arg(short, long, default_value = RAINBOW_TABLE_FILE)]
+    output: PathBuf,
+    /// 出力形式（省略時は拡張子 .json ならJSON、.rtm ならメモリマップ形式、.rtc なら圧縮形式、それ以外はバイナリ）
+    #[arg(long, value_enum)]
+    format: Option<FormatArg>,
+    /// ハッシュアルゴリズム
+    #[arg(short, long, value_enum, default_value_t = AlgorithmArg::Sha1)]
+    algorithm: AlgorithmArg,
+    /// 文字種の既定値
+    #[arg(long, value_enum, default_value_t = PresetArg::Alphanumeric)]
+    preset: PresetArg,
+    /// 文字種（指定した場合は --preset より優先）
+    #[arg(long)]
+    charset: Option<String>,
+    /// 先頭から順に位置ごとの文字種（複数指定可）
+    #[arg(long)]
+    position_charset: Vec<String>,
+    /// 平文の最小長
+    #[arg(long, default_value_t = 6)]
+    min_length: usize,
+    /// 平文の最大長
+    #[arg(long, default_value_t = 8)]
+    max_length: usize,
+    /// チェーンの長さ
+    #[arg(short = 'l', long, default_value_t = CHAIN_LENGTH)]
+    chain_length: usize,
+    /// リダクション関数
+    #[arg(long, value_enum, default_value_t = ReductionArg::Uniform)]
+    reduction: ReductionArg,
+    /// テーブル番号（リダクション関数の系列）
+    #[arg(short = 'i', long, default_value_t = 0)]
+    table_index: u32,
+    /// 終端ハッシュが重複したチェーンの扱い
+    #[arg(long, value_enum, default_value_t = CollisionArg::KeepLast)]
+    collision: CollisionArg,
+    /// 終端ハッシュごとにチェーンを1本だけ残す完全テーブルを生成する
+    #[arg(long)]
+    perfect: bool,
+    /// 完全テーブルのチェーン数の目標（不足分はキースペースから開始点を追加）
+    #[arg(long, requires = "perfect")]
+    target_chains: Option<usize>,
+    /// スレッド数（0 の場合は利用可能なCPU数）
+    #[arg(short = 'j', long, default_value_t = 0)]
+    threads: usize,
+    /// 途中経過を保存するチェックポイントファイル（既にある場合はその続きから生成し、完了後に削除する）
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// チェックポイントを書き出す間隔（秒）
+    #[arg(long, default_value_t = 60, requires = "checkpoint")]
+    checkpoint_interval: u64,
+    /// 進捗の表示方法（auto は標準エラー出力が端末なら in-place、それ以外は log）
+    #[arg(long, value_enum, default_value_t = ProgressArg::Auto)]
+    progress: ProgressArg,
+    /// 開始点の保存方法（index はパスワードリストの行番号、標準入力から読む場合はキースペースの通し番号）
+    #[arg(long, value_enum, default_value_t = StartStorageArg::Text)]
+    start_storage: StartStorageArg,
+    /// 終端ハッシュのうち保存する先頭のバイト数（省略時はすべて、短くするとテーブルは小さくなるが誤警報が増える）
+    #[arg(long)]
+    endpoint_bytes: Option<usize>,
+}
 
-    let charset = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
-    let charset_len = charset.len() as u32;
+#[derive(Args)]
+struct CrackArgs {
+    /// 照合するハッシュ値（16進数）
+    hashes: Vec<String>,
+    /// ハッシュ値のリストファイル（1行に1つ、- の場合は標準入力）
+    /// ハッシュ値もリストファイルも指定しない場合は標準入力から読み込む
+    #[arg(short, long, conflicts_with = "hashes")]
+    file: Option<PathBuf>,
+    /// 結果の出力先（- の場合は標準出力）
+    #[arg(short, long, default_value = STDIO_PATH)]
+    output: PathBuf,
+    /// 使用するテーブルファイル（複数指定可、- の場合は標準入力）
+    #[arg(short, long, default_value = RAINBOW_TABLE_FILE)]
+    table: Vec<PathBuf>,
+    /// リストファイルの解析方式
+    #[arg(long, value_enum, default_value_t = EngineArg::PerHash)]
+    engine: EngineArg,
+    /// スレッド数（0 の場合は利用可能なCPU数）
+    #[arg(short = 'j', long, default_value_t = 0)]
+    threads: usize,
+    /// リストファイルの解析の進捗の表示方法（auto は標準エラー出力が端末なら in-place、それ以外は log）
+    #[arg(long, value_enum, default_value_t = ProgressArg::Auto)]
+    progress: ProgressArg,
+    /// 開始点を行番号で保存したテーブルの生成に使ったパスワードリスト
+    /// （省略時はテーブルに記録されたファイル名がカレントディレクトリにあればそれを使う）
+    #[arg(long)]
+    wordlist: Option<PathBuf>,
+}
 
-    let length = 6 + (num % 3) as usize;
+#[derive(Args)]
+struct InfoArgs {
+    /// テーブルファイル
+    #[arg(required = true)]
+    tables: Vec<PathBuf>,
+}
 
-    let mut result = String::new();
-    for _ in 0..length {
-        let idx = (num % charset_len) as usize;
-        result.push(charset[idx] as char);
-        num /= charset_len;
-    }
+#[derive(Args)]
+struct ConvertArgs {
+    /// 変換元のテーブルファイル（- の場合は標準入力）
+    input: PathBuf,
+    /// 変換先のテーブルファイル（- の場合は標準出力）
+    output: PathBuf,
+    /// 変換先の形式（省略時は拡張子 .json ならJSON、.rtm ならメモリマップ形式、.rtc なら圧縮形式、それ以外はバイナリ）
+    #[arg(long, value_enum)]
+    format: Option<FormatArg>,
+    /// 変換先の開始点の保存方法（省略時は変換元と同じ）
+    /// index は --wordlist を指定すればその行番号、指定しなければキースペースの通し番号
+    #[arg(long, value_enum)]
+    start_storage: Option<StartStorageArg>,
+    /// 開始点の行番号に使うパスワードリスト（変換元が行番号で保存している場合は開始点の復元にも使う）
+    #[arg(long)]
+    wordlist: Option<PathBuf>,
+    /// 終端ハッシュを先頭のこのバイト数に切り詰める
+    #[arg(long)]
+    endpoint_bytes: Option<usize>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AlgorithmArg {
+    Sha1,
+    Ntlm,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PresetArg {
+    Lowercase,
+    Alphanumeric,
+    Printable,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReductionArg {
+    Uniform,
+    Legacy,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+#[allow(clippy::enum_variant_names)]
+enum CollisionArg {
+    KeepFirst,
+    KeepLast,
+    KeepAll,
+}
 
-    result
+#[derive(Clone, Copy, ValueEnum)]
+enum EngineArg {
+    PerHash,
+    SortMerge,
 }
 
-// ファイルからパスワードリストを読み込み、レインボーテーブルを生成
-fn generate_rainbow_table() -> io::Result<RainbowTable> {
-    let mut table = HashMap::new();
-    let file = File::open("list.txt")?;
-    let reader = io::BufReader::new(file);
+#[derive(Clone, Copy, ValueEnum)]
+enum ProgressArg {
+    Auto,
+    InPlace,
+    Log,
+    None,
+}
 
-    for (i, line) in reader.lines().enumerate() {
-        let start_text = line?;
-        let mut plaintext = start_text.clone();
-        for j in 0..CHAIN_LENGTH {
-            plaintext = reduce(&hash(&plaintext), j);
+impl From<ProgressArg> for ProgressStyle {
+    fn from(progress: ProgressArg) -> Self {
+        match progress {
+            ProgressArg::Auto => ProgressStyle::Auto,
+            ProgressArg::InPlace => ProgressStyle::InPlace,
+            ProgressArg::Log => ProgressStyle::Log,
+            ProgressArg::None => ProgressStyle::Silent,
         }
-        let end_hash = hash(&plaintext);
-        let end_hash_hex = hex::encode(end_hash); // ハッシュを16進数文字列に変換
-        table.insert(end_hash_hex, start_text);
+    }
+}
 
-        // 進捗表示
-        if i % 1000 == 0 {
-            println!("\rレインボーテーブルを生成中... {}行目", i);
+#[derive(Clone, Copy, ValueEnum)]
+enum StartStorageArg {
+    Text,
+    Index,
+}
+
+impl From<StartStorageArg> for StartStorage {
+    fn from(storage: StartStorageArg) -> Self {
+        match storage {
+            StartStorageArg::Text => StartStorage::Plaintext,
+            StartStorageArg::Index => StartStorage::Index,
         }
     }
+}
 
-    Ok(RainbowTable { table })
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Json,
+    Binary,
+    Mapped,
+    Compressed,
 }
 
-// レインボーテーブルをJSON形式で保存
-fn save_rainbow_table(rainbow_table: &RainbowTable) -> io::Result<()> {
-    let file = File::create(RAINBOW_TABLE_FILE)?;
-    serde_json::to_writer(file, rainbow_table)?;
-    Ok(())
+impl FormatArg {
+    fn resolve(format: Option<FormatArg>, path: &Path) -> TableFormat {
+        match format {
+            Some(FormatArg::Json) => TableFormat::Json,
+            Some(FormatArg::Binary) => TableFormat::Binary,
+            Some(FormatArg::Mapped) => TableFormat::Mapped,
+            Some(FormatArg::Compressed) => TableFormat::Compressed,
+            None => TableFormat::from_path(path),
+        }
+    }
 }
 
-// JSON形式のレインボーテーブルをファイルからロード
-fn load_rainbow_table() -> io::Result<RainbowTable> {
-    let file = File::open(RAINBOW_TABLE_FILE)?;
-    let rainbow_table = serde_json::from_reader(file)?;
-    Ok(rainbow_table)
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        None => run_demo(),
+        Some(Command::Generate(args)) => run_generate(args),
+        Some(Command::Crack(args)) => run_crack(args),
+        Some(Command::Info(args)) => run_info(args),
+        Some(Command::Convert(args)) => run_convert(args),
+    };
+    result.unwrap_or_else(|e| match e {
+        // 出力先のパイプが閉じられた場合（head などで途中まで読んだ場合）は正常に終了する
+        Error::Io(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        // 引数の値が不正な場合（clap の解析後に検証したもの）は clap の解析エラーと同じ終了コードにする
+        e @ Error::InvalidParameter(_) => {
+            eprintln!("エラー: {}", e);
+            ExitCode::from(EXIT_USAGE)
+        }
+        e => {
+            eprintln!("エラー: {}", e);
+            ExitCode::from(EXIT_ERROR)
+        }
+    })
 }
 
-// ハッシュ値からプレインテキストを復元
-fn crack_hash(rainbow_table: &RainbowTable, target_hash: &str) -> Option<String> {
-    let target_bytes = hex::decode(target_hash).expect("無効なハッシュ形式です");
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO_PATH
+}
 
-    // チェーンの逆方向から探索
-    for i in (0..CHAIN_LENGTH).rev() {
-        let mut current_hash = target_bytes.clone();
+// 2つのパスが同じファイルを指すか（どちらかが存在しない場合は false）
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
 
-        // 各ステップでリダクションとハッシュを繰り返し、テーブル内のエントリと照合
-        for j in i..CHAIN_LENGTH {
-            let candidate_text = reduce(&current_hash, j);
-            let hashed_candidate = hash(&candidate_text);
+// 入力を開く（- の場合は標準入力）
+fn open_input(path: &Path) -> Result<Box<dyn BufRead>> {
+    Ok(if is_stdio(path) {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(path)?))
+    })
+}
 
-            // レインボーテーブルで一致するエントリがあるか確認
-            if let Some(start_text) = rainbow_table.table.get(&hex::encode(&hashed_candidate)) {
-                let mut plaintext = start_text.clone();
+// テーブルを読み込む（- の場合は標準入力、ファイルはメモリマップ形式・圧縮形式なら展開せずにメモリマップする）
+fn open_table(path: &Path) -> Result<RainbowTable> {
+    if is_stdio(path) {
+        read_rainbow_table(io::stdin().lock())
+    } else {
+        load_rainbow_table(path)
+    }
+}
 
-                // 一致した場合、チェーンを開始からたどり、ターゲットハッシュと一致するか確認
-                for k in 0..CHAIN_LENGTH {
-                    if hash(&plaintext) == target_bytes {
-                        return Some(plaintext);
-                    }
-                    plaintext = reduce(&hash(&plaintext), k);
-                }
-            }
+// 出力を作成する（- の場合は標準出力）
+fn create_output(path: &Path) -> Result<Box<dyn Write>> {
+    Ok(if is_stdio(path) {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        Box::new(BufWriter::new(File::create(path)?))
+    })
+}
+
+// 進捗の残り時間を見積もるため入力の行数を数える（標準入力や進捗を表示しない場合は数えない）
+fn total_lines(path: &Path, progress: ProgressStyle) -> Result<Option<u64>> {
+    if is_stdio(path) || progress == ProgressStyle::Silent {
+        return Ok(None);
+    }
+    count_lines(path).map(Some)
+}
 
-            // 一致が見つからない場合、次のリダクションを生成してハッシュを更新
-            current_hash = hash(&reduce(&current_hash, j));
+// SIGINT/SIGTERM を受けたら中断を要求する（2回目はすぐに終了する）
+fn install_cancel_handler() -> Result<Arc<AtomicBool>> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let flag = cancel.clone();
+    ctrlc::set_handler(move || {
+        if flag.swap(true, Ordering::Relaxed) {
+            process::exit(EXIT_INTERRUPTED.into());
         }
+        eprintln!("\n中断しています... 処理中の計算を終えてから終了します（もう一度押すとすぐに終了します）");
+    })
+    .map_err(io::Error::other)?;
+    Ok(cancel)
+}
+
+// 開始点を行番号で保存したテーブルに、開始点の復元に使うパスワードリストを設定する
+// 指定がなければテーブルに記録されたファイル名を探す（同じファイルは1回だけ開く）
+fn attach_wordlists(tables: &mut [RainbowTable], wordlist: Option<&Path>) -> Result<()> {
+    let mut opened: Vec<(PathBuf, Arc<Wordlist>)> = Vec::new();
+    for table in tables {
+        let Some(fingerprint) = table.required_wordlist() else {
+            continue;
+        };
+        let path = match wordlist {
+            Some(path) => path.to_path_buf(),
+            None if Path::new(&fingerprint.name).is_file() => PathBuf::from(&fingerprint.name),
+            None => {
+                return Err(Error::InvalidParameter(format!(
+                    "開始点の復元にパスワードリスト {}（{} 行）が必要です。--wordlist で指定してください",
+                    fingerprint.name, fingerprint.lines
+                )))
+            }
+        };
+        let source = match opened.iter().find(|(p, _)| *p == path) {
+            Some((_, source)) => source.clone(),
+            None => {
+                let source = Arc::new(Wordlist::open(&path)?);
+                opened.push((path, source.clone()));
+                source
+            }
+        };
+        table.attach_wordlist(source)?;
+    }
+    Ok(())
+}
+
+// 終端ハッシュを切り詰めたことで増える誤警報の見積もりを表示する
+fn report_false_alarms(table: &RainbowTable) {
+    if let Some(estimate) = table.false_alarm_estimate() {
+        eprintln!("{}", estimate);
     }
+}
 
-    None // 一致するプレインテキストが見つからない場合
+// テーブルに記録されたアルゴリズム名から照合に使うハッシュアルゴリズムを取得
+fn hasher_for(algorithm: &str) -> Result<Box<dyn HashAlgorithm>> {
+    algorithm_by_name(algorithm).ok_or_else(|| {
+        Error::InvalidParameter(format!("未対応のハッシュアルゴリズムです: {}", algorithm))
+    })
 }
 
-fn main() -> io::Result<()> {
+// 引数なしで起動した場合のデモ（テーブルをロードまたは生成し、固定のハッシュ値を照合する）
+fn run_demo() -> Result<ExitCode> {
+    let params = TableParams::default();
     let rainbow_table = if fs::metadata(RAINBOW_TABLE_FILE).is_ok() {
         println!("既存のレインボーテーブルをロードしています...");
-        load_rainbow_table()?
+        // 既定値と異なるパラメータのテーブル（ヘッダのない旧形式を含む）も、テーブルのパラメータで照合する
+        load_rainbow_table(RAINBOW_TABLE_FILE)?
     } else {
         println!("新しいレインボーテーブルを生成しています...");
-        let table = generate_rainbow_table()?;
-        save_rainbow_table(&table)?;
+        let (table, report) = generate_rainbow_table(
+            &Sha1Hash,
+            &params,
+            WORDLIST_FILE,
+            &GenerateOptions::default(),
+        )?;
+        println!("{}", report);
+        save_rainbow_table(&table, RAINBOW_TABLE_FILE, TableFormat::Json)?;
         table
     };
     println!("レインボーテーブルのロードが完了しました");
 
+    // テーブルに記録されたアルゴリズムで照合する
+    let hasher = hasher_for(&rainbow_table.params().algorithm)?;
+
     // 適当な文字列 "casper4"　をハッシュ化・reduceしてみる -> Vuvk5CAA
     // これのハッシュ値 0da49c9a507b3a983d1804a675ae8cb9422746d7
 
     let target_hash = "0da49c9a507b3a983d1804a675ae8cb9422746d7";
-    if let Some(plaintext) = crack_hash(&rainbow_table, target_hash) {
+    if let Some(plaintext) =
+        crack_hash_with_threads(&rainbow_table, hasher.as_ref(), target_hash, 0)?
+    {
         println!("ハッシュ値からプレインテキストを特定: {}", plaintext);
+        Ok(ExitCode::SUCCESS)
     } else {
         println!("一致するプレインテキストが見つかりませんでした");
+        Ok(ExitCode::from(EXIT_NOT_CRACKED))
     }
+}
 
-    Ok(())
+fn run_generate(args: GenerateArgs) -> Result<ExitCode> {
+    let hasher: Box<dyn HashAlgorithm> = match args.algorithm {
+        AlgorithmArg::Sha1 => hasher_for("sha1")?,
+        AlgorithmArg::Ntlm => hasher_for("ntlm")?,
+    };
+    let keyspace = match (&args.charset, args.preset) {
+        (Some(charset), _) => Keyspace::new(charset, args.min_length, args.max_length)?,
+        (None, PresetArg::Lowercase) => Keyspace::lowercase(args.min_length, args.max_length)?,
+        (None, PresetArg::Alphanumeric) => {
+            Keyspace::alphanumeric(args.min_length, args.max_length)?
+        }
+        (None, PresetArg::Printable) => Keyspace::printable(args.min_length, args.max_length)?,
+    }
+    .with_position_charsets(args.position_charset)?;
+    let params = TableParams {
+        algorithm: hasher.name().to_string(),
+        keyspace,
+        chain_length: args.chain_length,
+        reduction: match args.reduction {
+            ReductionArg::Uniform => Reduction::Uniform,
+            ReductionArg::Legacy => Reduction::Legacy,
+        },
+        table_index: args.table_index,
+    };
+    let options = GenerateOptions {
+        collision_policy: match args.collision {
+            CollisionArg::KeepFirst => CollisionPolicy::KeepFirst,
+            CollisionArg::KeepLast => CollisionPolicy::KeepLast,
+            CollisionArg::KeepAll => CollisionPolicy::KeepAll,
+        },
+        perfect: args.perfect,
+        target_chains: args.target_chains,
+        threads: args.threads,
+        checkpoint: args.checkpoint.clone(),
+        checkpoint_interval: Duration::from_secs(args.checkpoint_interval),
+        cancel: Some(install_cancel_handler()?),
+        progress: args.progress.into(),
+        total_lines: total_lines(&args.wordlist, args.progress.into())?,
+        start_storage: args.start_storage.into(),
+        endpoint_len: args.endpoint_bytes,
+    };
+
+    let (table, report) = if is_stdio(&args.wordlist) {
+        let wordlist = open_input(&args.wordlist)?;
+        generate_rainbow_table_from_reader(hasher.as_ref(), &params, wordlist, &options)?
+    } else {
+        generate_rainbow_table(hasher.as_ref(), &params, &args.wordlist, &options)?
+    };
+    eprintln!("{}", report);
+    if args.endpoint_bytes.is_some() {
+        report_false_alarms(&table);
+    }
+    let format = FormatArg::resolve(args.format, &args.output);
+    write_rainbow_table(&table, create_output(&args.output)?, format)?;
+    eprintln!("テーブルを {} に保存しました", args.output.display());
+    // 中断した場合はチェックポイントを残し、同じコマンドで続きから生成できるようにする
+    if table.header.partial {
+        eprintln!("生成を中断したため、途中までのチェーンで部分的なテーブルを保存しました");
+        return Ok(ExitCode::from(EXIT_INTERRUPTED));
+    }
+    if let Some(checkpoint) = &args.checkpoint {
+        match fs::remove_file(checkpoint) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_crack(args: CrackArgs) -> Result<ExitCode> {
+    // ハッシュ値を指定しない場合はリストを標準入力から読み込む
+    let file = match (&args.file, args.hashes.is_empty()) {
+        (Some(file), _) => Some(file.clone()),
+        (None, true) => Some(PathBuf::from(STDIO_PATH)),
+        (None, false) => None,
+    };
+    let stdin_users = args.table.iter().chain(&file).filter(|p| is_stdio(p));
+    if stdin_users.count() > 1 {
+        return Err(Error::InvalidParameter(
+            "標準入力から読み込めるのはテーブルかハッシュ値のリストのどちらか1つです".to_string(),
+        ));
+    }
+
+    let mut tables = args
+        .table
+        .iter()
+        .map(|path| open_table(path))
+        .collect::<Result<Vec<RainbowTable>>>()?;
+    attach_wordlists(&mut tables, args.wordlist.as_deref())?;
+    let algorithm = &tables[0].params().algorithm;
+    if let Some(other) = tables.iter().find(|t| t.params().algorithm != *algorithm) {
+        return Err(Error::TableMismatch(format!(
+            "ハッシュアルゴリズムの異なるテーブルは同時に使用できません: {} と {}",
+            algorithm,
+            other.params().algorithm
+        )));
+    }
+    let hasher = hasher_for(algorithm)?;
+
+    let cancel = install_cancel_handler()?;
+    let mut output = create_output(&args.output)?;
+    if let Some(file) = &file {
+        let options = BatchOptions {
+            engine: match args.engine {
+                EngineArg::PerHash => BatchEngine::PerHash,
+                EngineArg::SortMerge => BatchEngine::SortMerge,
+            },
+            threads: args.threads,
+            cancel: Some(cancel.clone()),
+            progress: args.progress.into(),
+            total_lines: total_lines(file, args.progress.into())?,
+        };
+        let input = open_input(file)?;
+        let summary = crack_hash_reader(&tables, hasher.as_ref(), input, output, &options)?;
+        eprintln!("{}", summary);
+        return Ok(if cancel.load(Ordering::Relaxed) {
+            ExitCode::from(EXIT_INTERRUPTED)
+        } else if summary.cracked == summary.unique && summary.invalid == 0 {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::from(EXIT_NOT_CRACKED)
+        });
+    }
+
+    // 指定したハッシュ値は1件ずつ、チェーン上の位置を複数スレッドに分けて照合する
+    let mut all_cracked = true;
+    for target_hash in &args.hashes {
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(ExitCode::from(EXIT_INTERRUPTED));
+        }
+        match crack_hash_in_tables(&tables, hasher.as_ref(), target_hash, args.threads) {
+            Ok(Some(plaintext)) => writeln!(output, "{}\tcracked\t{}", target_hash, plaintext)?,
+            Ok(None) => {
+                writeln!(output, "{}\tnot_cracked", target_hash)?;
+                all_cracked = false;
+            }
+            // 16進数として不正な値や長さの異なる値は無効として出力し、残りの照合を続ける
+            Err(Error::InvalidHashEncoding(_) | Error::WrongDigestLength { .. }) => {
+                writeln!(output, "{}\tinvalid", target_hash)?;
+                all_cracked = false;
+            }
+            Err(e) => return Err(e),
+        }
+        output.flush()?;
+    }
+    Ok(if all_cracked {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(EXIT_NOT_CRACKED)
+    })
+}
+
+fn run_info(args: InfoArgs) -> Result<ExitCode> {
+    // パイプの読み手が先に終了した場合に備え、println! ではなく書き込みのエラーを返す
+    let mut out = io::stdout().lock();
+    for path in &args.tables {
+        let format = TableFormat::detect(path)?;
+        let table = load_rainbow_table(path)?;
+        let header = &table.header;
+        let params = table.params();
+        let keyspace = &params.keyspace;
+
+        writeln!(out, "{}", path.display())?;
+        writeln!(
+            out,
+            "  形式: {:?} ({} バイト)",
+            format,
+            fs::metadata(path)?.len()
+        )?;
+        writeln!(out, "  ハッシュアルゴリズム: {}", params.algorithm)?;
+        writeln!(
+            out,
+            "  キースペース: {:?} 長さ {}..={}",
+            keyspace.charset(),
+            keyspace.min_length(),
+            keyspace.max_length()
+        )?;
+        for (i, charset) in keyspace.position_charsets().iter().enumerate() {
+            writeln!(out, "    {} 文字目: {:?}", i + 1, charset)?;
+        }
+        writeln!(out, "  チェーン長: {}", params.chain_length)?;
+        writeln!(out, "  リダクション: {:?}", params.reduction)?;
+        writeln!(out, "  テーブル番号: {}", params.table_index)?;
+        writeln!(out, "  チェーン数: {}", header.chain_count)?;
+        if let Some(estimate) = table.false_alarm_estimate() {
+            if estimate.endpoint_len < estimate.digest_len {
+                writeln!(out, "  {}", estimate)?;
+            }
+        }
+        match &header.start_source {
+            StartSource::Plaintext => writeln!(out, "  開始点: プレインテキスト")?,
+            StartSource::Wordlist(fingerprint) => writeln!(
+                out,
+                "  開始点: パスワードリストの行番号（{}、{} 行、SHA-1 {}）",
+                fingerprint.name, fingerprint.lines, fingerprint.sha1
+            )?,
+            StartSource::Keyspace => writeln!(out, "  開始点: キースペースの通し番号")?,
+        }
+        if header.partial {
+            writeln!(
+                out,
+                "  部分的なテーブル（生成を中断したため、パスワードリストの一部のみ）"
+            )?;
+        }
+        writeln!(out, "  生成日時（UNIX時間）: {}", header.created_at)?;
+        // 衝突を無視した場合に、チェーンがたどる平文がキースペースに占める割合の上限
+        if let Some(size) = keyspace.size() {
+            let covered = header.chain_count as f64 * params.chain_length as f64;
+            writeln!(
+                out,
+                "  キースペースの大きさ: {}（網羅率の上限 {:.4}%）",
+                size,
+                (covered / size as f64 * 100.0).min(100.0)
+            )?;
+        }
+    }
+    out.flush()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_convert(args: ConvertArgs) -> Result<ExitCode> {
+    let format = FormatArg::resolve(args.format, &args.output);
+    // 入力と同じファイルに書き出す場合は、メモリマップしたまま上書きしないよう全体を読み込む
+    let mut table = if same_file(&args.input, &args.output) {
+        read_rainbow_table(open_input(&args.input)?)?
+    } else {
+        open_table(&args.input)?
+    };
+    // 保存方法を変えない場合は開始点を復元せずにそのまま書き出す
+    if let Some(storage) = args.start_storage {
+        attach_wordlists(std::slice::from_mut(&mut table), args.wordlist.as_deref())?;
+        let source = match (storage, &args.wordlist) {
+            (StartStorageArg::Index, Some(path)) => Some(Arc::new(Wordlist::open(path)?)),
+            _ => None,
+        };
+        table = table.with_start_storage(storage.into(), source)?;
+    }
+    if let Some(endpoint_bytes) = args.endpoint_bytes {
+        table = table.with_endpoint_len(endpoint_bytes)?;
+        report_false_alarms(&table);
+    }
+    write_rainbow_table(&table, create_output(&args.output)?, format)?;
+    eprintln!(
+        "{} を {} に変換しました",
+        args.input.display(),
+        args.output.display()
+    );
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,255 @@
+use crate::error::{Error, Result};
+use crate::table::{RainbowTable, StoredStart, TableHeader};
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::Write;
+use std::ops::Range;
+
+// メモリマップ形式のファイル先頭に置くマジックナンバーと形式のバージョン
+pub(crate) const MAPPED_MAGIC: &[u8; 4] = b"RBTM";
+const MAPPED_VERSION: u32 = 1;
+
+// メモリマップ形式のファイルの配置（整数はすべてリトルエンディアン）
+//   マジックナンバー(4) バージョン(u32) ヘッダの長さ(u64) ヘッダ(bincode)
+//   終端ハッシュのバイト長(u32) チェーン数(u64)
+//   終端ハッシュ（digest_len バイトずつ昇順に連結）
+//   開始プレインテキストの位置（チェーン数 + 1 個の u64）
+//   開始プレインテキスト（区切りなしで連結）
+// 開始点を番号で保存したテーブルでは、終端ハッシュの後に開始点の番号（チェーン数個の u64）を置く
+// 照合時は二分探索でたどるページだけが読み込まれるため、巨大なテーブルでもすぐに照合を始められる
+pub(crate) fn write_mapped<W: Write>(rainbow_table: &RainbowTable, writer: &mut W) -> Result<()> {
+    write_header(rainbow_table, MAPPED_MAGIC, MAPPED_VERSION, writer)?;
+    writer.write_all(&(rainbow_table.digest_len() as u32).to_le_bytes())?;
+    writer.write_all(&(rainbow_table.len() as u64).to_le_bytes())?;
+
+    // 各領域を順に書き出すため、チェーンを複数回たどる（メモリ上に複製しない）
+    for (end_hash, _) in rainbow_table.stored_chains() {
+        writer.write_all(&end_hash)?;
+    }
+    if rainbow_table.header.start_source.is_indexed() {
+        for (_, start) in rainbow_table.stored_chains() {
+            writer.write_all(&stored_index(start)?.to_le_bytes())?;
+        }
+        return Ok(());
+    }
+    let mut offset = 0u64;
+    writer.write_all(&offset.to_le_bytes())?;
+    for (_, start) in rainbow_table.stored_chains() {
+        offset += stored_text(start)?.len() as u64;
+        writer.write_all(&offset.to_le_bytes())?;
+    }
+    for (_, start) in rainbow_table.stored_chains() {
+        writer.write_all(stored_text(start)?)?;
+    }
+    Ok(())
+}
+
+// ヘッダの start_source に合った開始点であること
+pub(crate) fn stored_text(start: StoredStart<'_>) -> Result<&[u8]> {
+    match start {
+        StoredStart::Text(start_text) => Ok(start_text),
+        StoredStart::Index(_) => Err(corrupt("開始プレインテキストの代わりに番号があります")),
+    }
+}
+
+pub(crate) fn stored_index(start: StoredStart<'_>) -> Result<u64> {
+    match start {
+        StoredStart::Index(index) => Ok(index),
+        StoredStart::Text(_) => Err(corrupt(
+            "開始点の番号の代わりに開始プレインテキストがあります",
+        )),
+    }
+}
+
+// マジックナンバー・バージョン・長さ付きのヘッダを書き出す
+pub(crate) fn write_header<W: Write>(
+    rainbow_table: &RainbowTable,
+    magic: &[u8; 4],
+    version: u32,
+    writer: &mut W,
+) -> Result<()> {
+    let header = bincode::serialize(&rainbow_table.header)?;
+    writer.write_all(magic)?;
+    writer.write_all(&version.to_le_bytes())?;
+    writer.write_all(&(header.len() as u64).to_le_bytes())?;
+    writer.write_all(&header)?;
+    Ok(())
+}
+
+// メモリマップしたファイル、または読み込んだバイト列（標準入力から読み込んだ場合など）
+pub(crate) enum TableBytes {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl TableBytes {
+    // ファイルをメモリマップする
+    pub(crate) fn map(file: &File) -> Result<Self> {
+        // SAFETY: テーブルファイルは照合中に書き換えないものとして扱う
+        // （他のプロセスが書き換えた場合の内容は保証しない）
+        Ok(TableBytes::Mapped(unsafe { Mmap::map(file)? }))
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        match self {
+            TableBytes::Mapped(mmap) => mmap,
+            TableBytes::Owned(bytes) => bytes,
+        }
+    }
+}
+
+// メモリマップ形式のチェーンをファイル上の配置のまま参照する
+pub(crate) struct MappedChains {
+    bytes: TableBytes,
+    digest_len: usize,
+    len: usize,
+    endpoints: Range<usize>,
+    // 開始点を番号で保存している場合は番号の領域、そうでなければ開始プレインテキストの位置の領域
+    start_offsets: Range<usize>,
+    start_data: Range<usize>,
+    indexed: bool,
+}
+
+impl MappedChains {
+    // ヘッダとチェーンの位置を読み取る
+    pub(crate) fn parse(bytes: TableBytes) -> Result<(TableHeader, Self)> {
+        let data = bytes.as_slice();
+        let mut cursor = Cursor::new(data);
+        let header = cursor.header(MAPPED_MAGIC, MAPPED_VERSION, "メモリマップ形式")?;
+        let digest_len = cursor.u32()? as usize;
+        let len = cursor.len()?;
+        if digest_len == 0 || header.chain_count != len as u64 {
+            return Err(corrupt("チェーン数または終端ハッシュの長さが壊れています"));
+        }
+
+        let endpoints = cursor.range(len.checked_mul(digest_len))?;
+        // 番号で保存している場合は番号がチェーン数個、そうでなければ位置がチェーン数 + 1 個
+        let indexed = header.start_source.is_indexed();
+        let offset_count = if indexed {
+            Some(len)
+        } else {
+            len.checked_add(1)
+        };
+        let start_offsets = cursor.range(offset_count.and_then(|n| n.checked_mul(8)))?;
+        let start_data = cursor.pos..data.len();
+        let mapped = MappedChains {
+            bytes,
+            digest_len,
+            len,
+            endpoints,
+            start_offsets,
+            start_data,
+            indexed,
+        };
+        // 全体を読まずに済むよう、位置の検証は両端だけにする（途中の位置の破損は照合時に空の開始点として扱う）
+        if indexed {
+            if !mapped.start_data.is_empty() {
+                return Err(corrupt("開始点の番号の領域が壊れています"));
+            }
+        } else if mapped.start_offset(0) != 0
+            || mapped.start_offset(len) != mapped.start_data.len() as u64
+        {
+            return Err(corrupt("開始プレインテキストの領域が壊れています"));
+        }
+        Ok((header, mapped))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn digest_len(&self) -> usize {
+        self.digest_len
+    }
+
+    pub(crate) fn endpoint(&self, i: usize) -> &[u8] {
+        let start = self.endpoints.start + i * self.digest_len;
+        &self.bytes.as_slice()[start..start + self.digest_len]
+    }
+
+    pub(crate) fn start(&self, i: usize) -> StoredStart<'_> {
+        if self.indexed {
+            return StoredStart::Index(self.start_offset(i));
+        }
+        let data = &self.bytes.as_slice()[self.start_data.clone()];
+        let (begin, end) = (self.start_offset(i), self.start_offset(i + 1));
+        usize::try_from(begin)
+            .ok()
+            .zip(usize::try_from(end).ok())
+            .and_then(|(begin, end)| data.get(begin..end))
+            .map_or(StoredStart::Text(b""), StoredStart::Text)
+    }
+
+    // 開始プレインテキストの i 番目の位置（番号で保存している場合は i 番目の番号）
+    fn start_offset(&self, i: usize) -> u64 {
+        let pos = self.start_offsets.start + i * 8;
+        let bytes = &self.bytes.as_slice()[pos..pos + 8];
+        u64::from_le_bytes(bytes.try_into().unwrap())
+    }
+}
+
+// 先頭から順にバイト列を読み取る
+pub(crate) struct Cursor<'a> {
+    data: &'a [u8],
+    pub pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Cursor { data, pos: 0 }
+    }
+
+    // マジックナンバーとバージョンを確かめ、続くテーブルのヘッダを読み取る
+    pub(crate) fn header(
+        &mut self,
+        magic: &[u8; 4],
+        version: u32,
+        format_name: &str,
+    ) -> Result<TableHeader> {
+        if self.take(4)? != magic {
+            return Err(Error::CorruptTable(format!(
+                "{}のファイルではありません",
+                format_name
+            )));
+        }
+        let actual = self.u32()?;
+        if actual != version {
+            return Err(Error::CorruptTable(format!(
+                "未対応の{}のバージョンです: {}",
+                format_name, actual
+            )));
+        }
+        let header_len = self.len()?;
+        let header = self.take(header_len)?;
+        Ok(bincode::deserialize(header)?)
+    }
+
+    pub(crate) fn range(&mut self, len: Option<usize>) -> Result<Range<usize>> {
+        let end = len
+            .and_then(|len| self.pos.checked_add(len))
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| corrupt("ファイルが途中で終わっています"))?;
+        let range = self.pos..end;
+        self.pos = end;
+        Ok(range)
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let range = self.range(Some(len))?;
+        Ok(&self.data[range])
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn len(&mut self) -> Result<usize> {
+        let bytes = self.take(8)?.try_into().unwrap();
+        usize::try_from(u64::from_le_bytes(bytes))
+            .map_err(|_| corrupt("ファイルが途中で終わっています"))
+    }
+}
+
+pub(crate) fn corrupt(message: &str) -> Error {
+    Error::CorruptTable(message.to_string())
+}
@@ -0,0 +1,210 @@
+use crate::error::Result;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// 端末上で表示を更新する間隔と、端末以外へ1行ずつ書き出す間隔
+const IN_PLACE_INTERVAL: Duration = Duration::from_millis(200);
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+// 進捗表示の方法（表示先は標準エラー出力）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProgressStyle {
+    // 標準エラー出力が端末なら InPlace、それ以外は Log
+    #[default]
+    Auto,
+    // 同じ行を書き換えて表示する
+    InPlace,
+    // 一定間隔で1行ずつ書き出す
+    Log,
+    // 表示しない
+    Silent,
+}
+
+impl ProgressStyle {
+    fn resolve(self) -> Self {
+        match self {
+            ProgressStyle::Auto if io::stderr().is_terminal() => ProgressStyle::InPlace,
+            ProgressStyle::Auto => ProgressStyle::Log,
+            style => style,
+        }
+    }
+}
+
+// 処理量・速度・経過時間・残り時間の表示
+// 「生成中: 16384/40000 チェーン (41.0%) 5.2k チェーン/s 1.6M ハッシュ計算/s 経過 00:00:03 残り 00:00:04」
+pub(crate) struct ProgressReporter {
+    style: ProgressStyle,
+    label: &'static str,
+    unit: &'static str,
+    total: Option<u64>,
+    // 1単位あたりのハッシュ計算回数（チェーン長 + 1 など、分かる場合のみ）
+    hashes_per_unit: Option<u64>,
+    done: u64,
+    // 再開時など、この表示の開始前に済んでいた処理量（速度の計算から除く）
+    initial: u64,
+    started: Instant,
+    last_shown: Option<Instant>,
+    // 最後に表示した時点の処理量
+    shown_done: Option<u64>,
+}
+
+impl ProgressReporter {
+    pub(crate) fn new(
+        style: ProgressStyle,
+        label: &'static str,
+        unit: &'static str,
+        total: Option<u64>,
+    ) -> Self {
+        ProgressReporter {
+            style: style.resolve(),
+            label,
+            unit,
+            total,
+            hashes_per_unit: None,
+            done: 0,
+            initial: 0,
+            started: Instant::now(),
+            last_shown: None,
+            shown_done: None,
+        }
+    }
+
+    pub(crate) fn with_hashes_per_unit(mut self, hashes_per_unit: u64) -> Self {
+        self.hashes_per_unit = Some(hashes_per_unit);
+        self
+    }
+
+    // 開始時点で済んでいる処理量を設定
+    pub(crate) fn resume_from(&mut self, done: u64) {
+        self.done = done;
+        self.initial = done;
+    }
+
+    pub(crate) fn set_total(&mut self, total: Option<u64>) {
+        self.total = total;
+    }
+
+    // 処理量を更新し、前回の表示から間隔が空いていれば表示する
+    pub(crate) fn update(&mut self, done: u64) {
+        self.done = done;
+        let interval = match self.style {
+            ProgressStyle::InPlace => IN_PLACE_INTERVAL,
+            ProgressStyle::Log => LOG_INTERVAL,
+            _ => return,
+        };
+        if self
+            .last_shown
+            .is_some_and(|last_shown| last_shown.elapsed() < interval)
+        {
+            return;
+        }
+        self.show();
+        self.last_shown = Some(Instant::now());
+    }
+
+    // 進捗とあわせて知らせる1行のメッセージ（表示しない設定の場合は何もしない）
+    pub(crate) fn message(&self, message: &str) {
+        if self.style != ProgressStyle::Silent {
+            eprintln!("{}", message);
+        }
+    }
+
+    // 最終的な処理量を表示して終える（直前に同じ処理量を1行で表示済みなら繰り返さない）
+    pub(crate) fn finish(&mut self) {
+        match self.style {
+            ProgressStyle::Silent => return,
+            ProgressStyle::Log if self.shown_done == Some(self.done) => return,
+            _ => {}
+        }
+        self.show();
+        if self.style == ProgressStyle::InPlace {
+            eprintln!();
+        }
+    }
+
+    fn show(&mut self) {
+        self.shown_done = Some(self.done);
+        let line = self.line();
+        let mut stderr = io::stderr().lock();
+        // 表示に失敗しても処理は続ける
+        let _ = match self.style {
+            ProgressStyle::InPlace => write!(stderr, "\r{}\x1b[K", line),
+            _ => writeln!(stderr, "{}", line),
+        };
+        let _ = stderr.flush();
+    }
+
+    fn line(&self) -> String {
+        let elapsed = self.started.elapsed();
+        let secs = elapsed.as_secs_f64();
+        let rate = if secs > 0.0 {
+            (self.done - self.initial) as f64 / secs
+        } else {
+            0.0
+        };
+
+        let mut line = format!("{}中: {}", self.label, self.done);
+        if let Some(total) = self.total {
+            line += &format!("/{}", total);
+        }
+        line += &format!(" {}", self.unit);
+        if let Some(total) = self.total.filter(|&total| total > 0) {
+            let percent = (self.done as f64 / total as f64 * 100.0).min(100.0);
+            line += &format!(" ({:.1}%)", percent);
+        }
+        line += &format!(" {} {}/s", format_rate(rate), self.unit);
+        if let Some(hashes_per_unit) = self.hashes_per_unit {
+            line += &format!(
+                " {} ハッシュ計算/s",
+                format_rate(rate * hashes_per_unit as f64)
+            );
+        }
+        line += &format!(" 経過 {}", format_duration(elapsed));
+        if let Some(total) = self.total {
+            if rate > 0.0 && total > self.done {
+                let remaining = (total - self.done) as f64 / rate;
+                line += &format!(
+                    " 残り {}",
+                    format_duration(Duration::from_secs_f64(remaining))
+                );
+            }
+        }
+        line
+    }
+}
+
+// 1秒あたりの量を k, M, G の接頭辞付きで表す
+fn format_rate(rate: f64) -> String {
+    match rate {
+        r if r >= 1e9 => format!("{:.1}G", r / 1e9),
+        r if r >= 1e6 => format!("{:.1}M", r / 1e6),
+        r if r >= 1e3 => format!("{:.1}k", r / 1e3),
+        r => format!("{:.1}", r),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+// ファイルの行数を数える（進捗表示の総数に使う）
+pub fn count_lines<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut count = 0;
+    let mut last = b'\n';
+    loop {
+        let buf = reader.fill_buf()?;
+        let Some(&byte) = buf.last() else {
+            break;
+        };
+        count += buf.iter().filter(|&&b| b == b'\n').count() as u64;
+        last = byte;
+        let len = buf.len();
+        reader.consume(len);
+    }
+    // 末尾に改行のない最終行も1行と数える
+    Ok(count + (last != b'\n') as u64)
+}
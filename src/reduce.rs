@@ -0,0 +1,136 @@
+use crate::error::{Error, Result};
+use crate::keyspace::Keyspace;
+use serde::{Deserialize, Serialize};
+
+// テーブル番号とチェーンの位置ごとにリダクション関数を変えるための定数（黄金比由来の奇数）
+const POSITION_MULTIPLIER: u128 = 0x9e37_79b9_7f4a_7c15_f39c_c060_5ced_c835;
+const LEGACY_TABLE_MULTIPLIER: u32 = 0x9e37_79b9;
+
+// リダクション関数の種類
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reduction {
+    // 初期実装のリダクション（ダイジェスト先頭8バイトのみ使用、出力に偏りあり）
+    Legacy,
+    // ダイジェスト全体から128ビット整数を作り、キースペース全体へ一様に写像する
+    #[default]
+    Uniform,
+}
+
+impl Reduction {
+    // 旧形式のファイルに記録されていない場合の値
+    pub fn legacy() -> Self {
+        Reduction::Legacy
+    }
+}
+
+// キースペースとリダクションの種類から作る、ハッシュ値→平文の変換器
+// テーブル番号が異なるとリダクション関数の系列も異なり、互いに独立したテーブルになる
+#[derive(Clone, Debug)]
+pub struct Reducer {
+    keyspace: Keyspace,
+    reduction: Reduction,
+    table_index: u32,
+    // 長さごとの平文の個数（短い順）
+    length_counts: Vec<u128>,
+    size: u128,
+}
+
+impl Reducer {
+    pub fn new(keyspace: &Keyspace, reduction: Reduction, table_index: u32) -> Result<Self> {
+        let size = keyspace.size().ok_or_else(|| {
+            Error::InvalidParameter(
+                "キースペースが大きすぎます（2^128 通り以下にしてください）".to_string(),
+            )
+        })?;
+        let length_counts = (keyspace.min_length()..=keyspace.max_length())
+            .map(|length| keyspace.count_of_length(length).unwrap_or(u128::MAX))
+            .collect();
+        Ok(Reducer {
+            keyspace: keyspace.clone(),
+            reduction,
+            table_index,
+            length_counts,
+            size,
+        })
+    }
+
+    pub fn keyspace(&self) -> &Keyspace {
+        &self.keyspace
+    }
+
+    pub fn reduction(&self) -> Reduction {
+        self.reduction
+    }
+
+    pub fn table_index(&self) -> u32 {
+        self.table_index
+    }
+
+    // ハッシュ値をキースペース内の平文に変換
+    pub fn reduce(&self, hash: &[u8], position: usize) -> String {
+        let mut result = String::new();
+        self.reduce_into(hash, position, &mut result);
+        result
+    }
+
+    // reduce の結果を out に書き込む（チェーン計算でのアロケーションを避ける）
+    pub fn reduce_into(&self, hash: &[u8], position: usize, out: &mut String) {
+        out.clear();
+        match self.reduction {
+            Reduction::Legacy => self.reduce_legacy(hash, position, out),
+            Reduction::Uniform => self.reduce_uniform(hash, position, out),
+        }
+    }
+
+    fn reduce_legacy(&self, hash: &[u8], position: usize, out: &mut String) {
+        let keyspace = &self.keyspace;
+        // テーブル番号 0 では初期実装と同じ結果になる
+        let table_salt = self.table_index.wrapping_mul(LEGACY_TABLE_MULTIPLIER);
+        // 8バイトに満たないダイジェストは0で埋める
+        let mut head = [0u8; 8];
+        let len = hash.len().min(8);
+        head[..len].copy_from_slice(&hash[..len]);
+        let mut num = u32::from_be_bytes([head[0], head[1], head[2], head[3]])
+            ^ (position as u32)
+            ^ table_salt;
+        num = num.wrapping_add(u32::from_be_bytes([head[4], head[5], head[6], head[7]]));
+
+        let length_span = (keyspace.max_length() - keyspace.min_length() + 1) as u32;
+        let length = keyspace.min_length() + (num % length_span) as usize;
+
+        for i in 0..length {
+            let charset = keyspace.charset_at(i);
+            let charset_len = charset.len() as u32;
+            let idx = (num % charset_len) as usize;
+            out.push(charset[idx] as char);
+            num /= charset_len;
+        }
+    }
+
+    // ダイジェスト全体を128ビットに畳み込み、キースペースの通し番号として解釈する
+    // 長さは各長さの平文の個数に比例して選ばれ、各文字は通し番号の別々の桁から取り出す
+    fn reduce_uniform(&self, hash: &[u8], position: usize, out: &mut String) {
+        let mut num = 0u128;
+        for chunk in hash.chunks(16) {
+            let mut block = [0u8; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            num ^= u128::from_be_bytes(block);
+        }
+        let salt = ((self.table_index as u128) << 64) | position as u128;
+        num ^= (salt + 1).wrapping_mul(POSITION_MULTIPLIER);
+
+        // 2^128 を size で割った余りによる偏りは高々 size / 2^128
+        let mut index = num % self.size;
+        let mut length = self.keyspace.min_length();
+        for &count in &self.length_counts {
+            if index < count {
+                break;
+            }
+            index -= count;
+            length += 1;
+        }
+
+        self.keyspace.write_plaintext(index, length, out);
+    }
+}
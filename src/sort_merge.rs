@@ -0,0 +1,284 @@
+use crate::crack::check_algorithm;
+use crate::error::Result;
+use crate::generate::{cancelled, worker_count};
+use crate::hash::HashAlgorithm;
+use crate::reduce::Reducer;
+use crate::table::RainbowTable;
+use std::sync::atomic::{self, AtomicBool, AtomicUsize};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// 一度にソートする候補終端ハッシュの最大数（ターゲットをこの数に収まるよう分割して処理する）
+const CANDIDATE_LIMIT: usize = 1 << 22;
+
+// ターゲットごとに全位置の候補終端ハッシュを計算してソートし、テーブルの終端ハッシュと
+// 1回の順次走査で突き合わせる。一致したものだけチェーンをたどって確認する
+// 戻り値はターゲットと同じ順序で、復元できたものは Some(Some)
+// 中断は分割した塊の間で確かめ、照合しなかった残りのターゲットは None
+// progress には塊を1つ照合し終えるごとに、照合の済んだターゲットの数を渡す
+pub(crate) fn crack_targets_sort_merge<H: HashAlgorithm + ?Sized>(
+    rainbow_table: &RainbowTable,
+    hasher: &H,
+    targets: &[Vec<u8>],
+    threads: usize,
+    cancel: &Option<Arc<AtomicBool>>,
+    progress: &mut dyn FnMut(usize),
+) -> Result<Vec<Option<Option<String>>>> {
+    check_algorithm(rainbow_table, hasher)?;
+    rainbow_table.check_starts()?;
+    let reducer = rainbow_table.reducer()?;
+    let chain_length = rainbow_table.params().chain_length;
+    let threads = worker_count(threads);
+    let chunk_size = (CANDIDATE_LIMIT / chain_length.max(1)).max(1);
+
+    let mut results = Vec::with_capacity(targets.len());
+    for chunk in targets.chunks(chunk_size) {
+        if cancelled(cancel) {
+            break;
+        }
+        let candidates = Candidates::compute(hasher, &reducer, chain_length, chunk, threads);
+        let matches = candidates.merge_join(rainbow_table);
+        results.extend(
+            verify_matches(rainbow_table, hasher, &reducer, chunk, matches, threads)
+                .into_iter()
+                .map(Some),
+        );
+        progress(results.len());
+    }
+    results.resize(targets.len(), None);
+    Ok(results)
+}
+
+// ターゲットがチェーンの i 番目にあると仮定したときの終端ハッシュの集合
+struct Candidates {
+    digest_len: usize,
+    // 候補 c の終端ハッシュは ends[c * digest_len..(c + 1) * digest_len]
+    ends: Vec<u8>,
+    // 候補 c の（ターゲット番号, 位置）
+    origins: Vec<(u32, u32)>,
+    // 終端ハッシュの昇順に並べた候補番号
+    order: Vec<u32>,
+}
+
+impl Candidates {
+    fn compute<H: HashAlgorithm + ?Sized>(
+        hasher: &H,
+        reducer: &Reducer,
+        chain_length: usize,
+        targets: &[Vec<u8>],
+        threads: usize,
+    ) -> Self {
+        let digest_len = hasher.digest_len();
+        let per_target = chain_length * digest_len;
+        let mut ends = vec![0; targets.len() * per_target];
+
+        // ターゲットごとの候補をスレッドに分担して計算
+        let per_thread = targets.len().div_ceil(threads).max(1);
+        thread::scope(|scope| {
+            for (targets, ends) in targets
+                .chunks(per_thread)
+                .zip(ends.chunks_mut(per_thread * per_target))
+            {
+                scope.spawn(move || {
+                    let mut plaintext = String::new();
+                    for (target, ends) in targets.iter().zip(ends.chunks_mut(per_target)) {
+                        for (i, end_hash) in ends.chunks_mut(digest_len).enumerate() {
+                            end_hash.copy_from_slice(target);
+                            for j in i..chain_length {
+                                reducer.reduce_into(end_hash, j, &mut plaintext);
+                                hasher.hash_into(plaintext.as_bytes(), end_hash);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        let origins: Vec<(u32, u32)> = (0..targets.len() as u32)
+            .flat_map(|t| (0..chain_length as u32).map(move |i| (t, i)))
+            .collect();
+        let end_of = |c: u32| &ends[c as usize * digest_len..(c as usize + 1) * digest_len];
+        let mut order: Vec<u32> = (0..origins.len() as u32).collect();
+        order.sort_unstable_by(|&a, &b| end_of(a).cmp(end_of(b)));
+
+        Candidates {
+            digest_len,
+            ends,
+            origins,
+            order,
+        }
+    }
+
+    fn end(&self, c: u32) -> &[u8] {
+        &self.ends[c as usize * self.digest_len..(c as usize + 1) * self.digest_len]
+    }
+
+    // ソート済みの候補とテーブルの終端ハッシュを先頭から順に突き合わせ、
+    // 一致した（ターゲット番号, 位置, チェーン番号）を返す
+    // 終端ハッシュを切り詰めたテーブルでは候補の先頭だけを比べる（先頭で比べても並び順は変わらない）
+    // テーブルは先頭から順に1回だけ読む（圧縮形式でバケットを繰り返し復号しないように）
+    fn merge_join(&self, rainbow_table: &RainbowTable) -> Vec<(u32, u32, usize)> {
+        let endpoint_len = rainbow_table.digest_len();
+        let end = |a: usize| &self.end(self.order[a])[..endpoint_len];
+        let mut matches = Vec::new();
+        let mut a = 0;
+        let mut endpoints = rainbow_table
+            .stored_chains()
+            .map(|(end_hash, _)| end_hash)
+            .enumerate()
+            .peekable();
+        while a < self.order.len() {
+            let Some((b, end_hash)) = endpoints.next() else {
+                break;
+            };
+            // 同じ終端ハッシュのチェーンが複数ある場合はすべて候補にする
+            let mut run_end = b + 1;
+            while endpoints.next_if(|(_, next)| *next == end_hash).is_some() {
+                run_end += 1;
+            }
+            while a < self.order.len() && end(a) < &*end_hash {
+                a += 1;
+            }
+            while a < self.order.len() && end(a) == &*end_hash {
+                let (t, i) = self.origins[self.order[a] as usize];
+                matches.extend((b..run_end).map(|k| (t, i, k)));
+                a += 1;
+            }
+        }
+        matches
+    }
+}
+
+// 一致した候補のチェーンを開始から位置 i までたどり、ターゲットと一致するか確認する
+// 単独の解析と同じく終端に近い位置から順に確認する
+fn verify_matches<H: HashAlgorithm + ?Sized>(
+    rainbow_table: &RainbowTable,
+    hasher: &H,
+    reducer: &Reducer,
+    targets: &[Vec<u8>],
+    mut matches: Vec<(u32, u32, usize)>,
+    threads: usize,
+) -> Vec<Option<String>> {
+    matches.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+    let mut per_target = vec![Vec::new(); targets.len()];
+    for (t, i, k) in matches {
+        per_target[t as usize].push((i as usize, k));
+    }
+
+    let results = Mutex::new(vec![None; targets.len()]);
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..threads.min(targets.len()) {
+            scope.spawn(|| {
+                let mut digest = vec![0; hasher.digest_len()];
+                loop {
+                    let t = next.fetch_add(1, atomic::Ordering::Relaxed);
+                    if t >= targets.len() {
+                        return;
+                    }
+                    let found = per_target[t].iter().find_map(|&(i, k)| {
+                        let mut plaintext = rainbow_table.start(k).to_string();
+                        for j in 0..i {
+                            hasher.hash_into(plaintext.as_bytes(), &mut digest);
+                            reducer.reduce_into(&digest, j, &mut plaintext);
+                        }
+                        hasher.hash_into(plaintext.as_bytes(), &mut digest);
+                        (digest == targets[t]).then_some(plaintext)
+                    });
+                    results.lock().unwrap()[t] = found;
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::{crack_hash_list, BatchEngine, BatchOptions, BatchStatus};
+    use crate::generate::GenerateOptions;
+    use crate::hash::{HashAlgorithm, Sha1Hash};
+    use crate::progress::ProgressStyle;
+    use crate::table::{CollisionPolicy, RainbowTable};
+    use crate::test_util;
+
+    // チェーンの様々な位置の平文のハッシュ値と、テーブルにない平文のハッシュ値を並べた入力
+    // （重複した行と無効な行も含める）
+    fn hash_lines(rainbow_table: &RainbowTable) -> Vec<String> {
+        let reducer = rainbow_table.reducer().unwrap();
+        let chain_length = rainbow_table.params().chain_length;
+        let mut lines = Vec::new();
+        for k in (0..rainbow_table.len()).step_by(7) {
+            let i = k * 13 % chain_length;
+            let mut plaintext = rainbow_table.start(k).into_owned();
+            for j in 0..i {
+                reducer.reduce_into(&Sha1Hash.hash(plaintext.as_bytes()), j, &mut plaintext);
+            }
+            lines.push(hex::encode(Sha1Hash.hash(plaintext.as_bytes())));
+        }
+        for plaintext in ["zzzzzzz", "absent", "1234"] {
+            lines.push(hex::encode(Sha1Hash.hash(plaintext.as_bytes())));
+        }
+        lines.push(lines[0].to_uppercase());
+        lines.push("not a hash".to_string());
+        lines
+    }
+
+    // ソートマージとハッシュ値ごとの解析で、行ごとの結果が一致すること
+    fn assert_engines_agree(rainbow_table: RainbowTable) {
+        let lines = hash_lines(&rainbow_table);
+        let rainbow_tables = [rainbow_table];
+        let crack = |engine| {
+            let options = BatchOptions {
+                engine,
+                threads: 4,
+                progress: ProgressStyle::Silent,
+                ..Default::default()
+            };
+            crack_hash_list(&rainbow_tables, &Sha1Hash, &lines, &options).unwrap()
+        };
+        let (per_hash, per_hash_summary) = crack(BatchEngine::PerHash);
+        let (sort_merge, sort_merge_summary) = crack(BatchEngine::SortMerge);
+        assert_eq!(sort_merge, per_hash);
+        assert_eq!(sort_merge_summary, per_hash_summary);
+
+        // チェーンから取り出したハッシュ値はすべて復元できる
+        let from_chains = lines.len() - 5;
+        assert!(sort_merge[..from_chains]
+            .iter()
+            .all(|status| matches!(status, BatchStatus::Cracked(_))));
+        assert_eq!(sort_merge[lines.len() - 1], BatchStatus::Invalid);
+    }
+
+    #[test]
+    fn matches_per_hash() {
+        assert_engines_agree(test_util::table(300, GenerateOptions::default()));
+    }
+
+    #[test]
+    fn matches_per_hash_with_duplicate_endpoints() {
+        // 終端ハッシュを1バイトに切り詰めると、多くのチェーンが同じ終端ハッシュを共有する
+        let rainbow_table = test_util::table(
+            600,
+            GenerateOptions {
+                collision_policy: CollisionPolicy::KeepAll,
+                endpoint_len: Some(1),
+                ..Default::default()
+            },
+        );
+        let ends: Vec<_> = rainbow_table.stored_chains().map(|(end, _)| end).collect();
+        assert!(ends.windows(2).any(|pair| pair[0] == pair[1]));
+        assert_engines_agree(rainbow_table);
+    }
+
+    #[test]
+    fn matches_per_hash_with_truncated_endpoints() {
+        assert_engines_agree(test_util::table(
+            300,
+            GenerateOptions {
+                endpoint_len: Some(4),
+                ..Default::default()
+            },
+        ));
+    }
+}
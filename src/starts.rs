@@ -0,0 +1,147 @@
+use crate::error::{Error, Result};
+use crate::mapped::TableBytes;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+// 行の位置を記録する間隔（この行数ごとの行頭だけを覚え、間の行は改行を数えて探す）
+const LINE_SAMPLE: u64 = 64;
+
+// テーブルに保存する開始点の表し方
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StartStorage {
+    // 開始プレインテキストをそのまま保存する
+    #[default]
+    Plaintext,
+    // パスワードリストの行番号、またはキースペースの通し番号を保存し、照合時に開始プレインテキストを復元する
+    Index,
+}
+
+// テーブルのヘッダに記録する、開始点の番号の意味
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StartSource {
+    // 開始プレインテキストをそのまま保存している
+    #[default]
+    Plaintext,
+    // パスワードリストの行番号（0始まり）
+    // 行数以上の番号は、完全テーブルでリストの後に追加した開始点（キースペースの通し番号 + 行数）
+    Wordlist(WordlistFingerprint),
+    // キースペースの通し番号（Keyspace::nth の番号）
+    Keyspace,
+}
+
+impl StartSource {
+    // 開始点を番号で保存しているか
+    pub fn is_indexed(&self) -> bool {
+        *self != StartSource::Plaintext
+    }
+}
+
+// 生成に使ったパスワードリストを照合時に確かめるための情報
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WordlistFingerprint {
+    // ファイル名（表示用、照合には使わない）
+    pub name: String,
+    // 行数
+    pub lines: u64,
+    // ファイル全体のSHA-1（16進数）
+    pub sha1: String,
+}
+
+// 行番号から開始プレインテキストを取り出すためのパスワードリスト
+// ファイルはメモリマップし、一定間隔の行頭の位置だけをメモリに持つ
+pub struct Wordlist {
+    bytes: TableBytes,
+    fingerprint: WordlistFingerprint,
+    // LINE_SAMPLE 行ごとの行頭の位置
+    samples: Vec<usize>,
+}
+
+impl Wordlist {
+    // パスワードリストを開き、行数とSHA-1を計算する
+    // 行の区切りは生成時の読み込み（BufRead::lines）と同じく \n または \r\n
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = TableBytes::map(&File::open(path)?)?;
+        let data = bytes.as_slice();
+
+        let mut samples = Vec::new();
+        let mut lines = 0u64;
+        let mut line_start = 0;
+        for (pos, _) in data.iter().enumerate().filter(|(_, &b)| b == b'\n') {
+            if lines.is_multiple_of(LINE_SAMPLE) {
+                samples.push(line_start);
+            }
+            lines += 1;
+            line_start = pos + 1;
+        }
+        // 末尾に改行のない最終行も1行と数える
+        if line_start < data.len() {
+            if lines.is_multiple_of(LINE_SAMPLE) {
+                samples.push(line_start);
+            }
+            lines += 1;
+        }
+
+        let fingerprint = WordlistFingerprint {
+            name: path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into(),
+            ),
+            lines,
+            sha1: hex::encode(Sha1::digest(data)),
+        };
+        Ok(Wordlist {
+            bytes,
+            fingerprint,
+            samples,
+        })
+    }
+
+    pub fn fingerprint(&self) -> &WordlistFingerprint {
+        &self.fingerprint
+    }
+
+    // 行数
+    pub fn lines(&self) -> u64 {
+        self.fingerprint.lines
+    }
+
+    // index 行目（0始まり）の内容（行末の改行を除く）
+    pub fn line(&self, index: u64) -> Option<&[u8]> {
+        if index >= self.lines() {
+            return None;
+        }
+        let data = self.bytes.as_slice();
+        let start = self.samples[(index / LINE_SAMPLE) as usize];
+        let line = data[start..]
+            .split(|&b| b == b'\n')
+            .nth((index % LINE_SAMPLE) as usize)?;
+        Some(line.strip_suffix(b"\r").unwrap_or(line))
+    }
+
+    // 各行の内容から行番号を引く表（同じ内容の行は最初の行番号）
+    pub(crate) fn line_indices(&self) -> HashMap<&[u8], u64> {
+        let mut indices = HashMap::with_capacity(self.lines() as usize);
+        let lines = self.bytes.as_slice().split(|&b| b == b'\n');
+        for (index, line) in (0..self.lines()).zip(lines) {
+            indices
+                .entry(line.strip_suffix(b"\r").unwrap_or(line))
+                .or_insert(index);
+        }
+        indices
+    }
+
+    // テーブルの記録と同じパスワードリストか確かめる
+    pub fn check(&self, expected: &WordlistFingerprint) -> Result<()> {
+        if self.fingerprint.lines != expected.lines || self.fingerprint.sha1 != expected.sha1 {
+            return Err(Error::TableMismatch(format!(
+                "パスワードリスト {} がテーブルの生成に使ったもの（{}、{} 行）と異なります",
+                self.fingerprint.name, expected.name, expected.lines
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,750 @@
+use crate::compressed::CompressedChains;
+use crate::error::{Error, Result};
+use crate::hash::{algorithm_by_name, HashAlgorithm, Sha1Hash};
+use crate::keyspace::Keyspace;
+use crate::mapped::MappedChains;
+use crate::reduce::{Reducer, Reduction};
+use crate::starts::{StartSource, StartStorage, Wordlist, WordlistFingerprint};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// レインボーチェーンの長さ（既定値）
+pub const CHAIN_LENGTH: usize = 300;
+
+// テーブル生成のパラメータ
+// いずれかが異なるとチェーンの計算結果が変わるため、テーブルと一緒に保存して照合する
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableParams {
+    // ハッシュアルゴリズム名
+    pub algorithm: String,
+    // リダクション関数の出力空間
+    pub keyspace: Keyspace,
+    // チェーンの長さ
+    pub chain_length: usize,
+    // リダクション関数の種類
+    pub reduction: Reduction,
+    // リダクション関数の系列を選ぶテーブル番号
+    pub table_index: u32,
+}
+
+impl TableParams {
+    // 初期実装（ヘッダのない旧形式のファイル）のパラメータ
+    pub fn legacy() -> Self {
+        TableParams {
+            algorithm: Sha1Hash.name().to_string(),
+            keyspace: Keyspace::legacy(),
+            chain_length: CHAIN_LENGTH,
+            reduction: Reduction::Legacy,
+            table_index: 0,
+        }
+    }
+
+    // キースペースとリダクションの変換器を作成
+    pub fn reducer(&self) -> Result<Reducer> {
+        Reducer::new(&self.keyspace, self.reduction, self.table_index)
+    }
+
+    // 他のパラメータと異なる項目の説明を列挙
+    pub fn mismatches(&self, other: &TableParams) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if self.algorithm != other.algorithm {
+            mismatches.push(format!(
+                "ハッシュアルゴリズム: {} ≠ {}",
+                self.algorithm, other.algorithm
+            ));
+        }
+        if self.keyspace != other.keyspace {
+            mismatches.push(format!(
+                "キースペース: {:?} ≠ {:?}",
+                self.keyspace, other.keyspace
+            ));
+        }
+        if self.chain_length != other.chain_length {
+            mismatches.push(format!(
+                "チェーン長: {} ≠ {}",
+                self.chain_length, other.chain_length
+            ));
+        }
+        if self.reduction != other.reduction {
+            mismatches.push(format!(
+                "リダクション: {:?} ≠ {:?}",
+                self.reduction, other.reduction
+            ));
+        }
+        if self.table_index != other.table_index {
+            mismatches.push(format!(
+                "テーブル番号: {} ≠ {}",
+                self.table_index, other.table_index
+            ));
+        }
+        mismatches
+    }
+}
+
+impl Default for TableParams {
+    fn default() -> Self {
+        TableParams {
+            algorithm: Sha1Hash.name().to_string(),
+            keyspace: Keyspace::legacy(),
+            chain_length: CHAIN_LENGTH,
+            reduction: Reduction::Uniform,
+            table_index: 0,
+        }
+    }
+}
+
+// 生成時に終端ハッシュが重複したチェーンの扱い
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    // 最初に現れたチェーンを残す
+    KeepFirst,
+    // 最後に現れたチェーンを残す（初期実装の HashMap への上書きと同じ）
+    #[default]
+    KeepLast,
+    // 開始プレインテキストが異なるチェーンはすべて残す
+    KeepAll,
+}
+
+// 終端ハッシュの重複の集計
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollisionReport {
+    // 計算したチェーン数
+    pub chains: usize,
+    // 同じ開始プレインテキストから計算した（完全に同一の）チェーン数
+    pub duplicate_starts: usize,
+    // 開始プレインテキストは異なるが、他のチェーンと終端ハッシュが一致したチェーン数
+    pub merged_chains: usize,
+    // テーブルに保存したチェーン数
+    pub stored: usize,
+}
+
+impl CollisionReport {
+    // 保存しなかったチェーン数
+    pub fn dropped(&self) -> usize {
+        self.chains - self.stored
+    }
+}
+
+impl fmt::Display for CollisionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "チェーン {} 本のうち {} 本を保存（同一の開始点 {} 本、終端の衝突 {} 本、破棄 {} 本）",
+            self.chains,
+            self.stored,
+            self.duplicate_starts,
+            self.merged_chains,
+            self.dropped()
+        )
+    }
+}
+
+// 終端ハッシュを先頭の数バイトに切り詰めたことで増える誤警報の見積もり
+// 切り詰めた終端ハッシュが一様に分布するとして、探索したハッシュ値の先頭が無関係なチェーンと一致する回数を数える
+#[derive(Clone, Debug, PartialEq)]
+pub struct FalseAlarmEstimate {
+    // 保存している終端ハッシュのバイト長
+    pub endpoint_len: usize,
+    // ハッシュ値のバイト長
+    pub digest_len: usize,
+    // テーブルの探索1回あたりの追加の誤警報の期待値
+    pub per_lookup: f64,
+    // ハッシュ値1件の照合（チェーン上の位置ごとに終端で1回、チェーン長の回数の探索）あたりの追加の誤警報の期待値
+    pub per_hash: f64,
+    // 誤警報のチェーンをたどるための、ハッシュ値1件あたりの追加のハッシュ計算回数の期待値（上限）
+    pub extra_hashes: f64,
+}
+
+impl fmt::Display for FalseAlarmEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "終端ハッシュ {}/{} バイト: 追加の誤警報の期待値は探索1回あたり {:.3e} 回、ハッシュ値1件あたり {:.3e} 回（追加のハッシュ計算 最大 {:.3e} 回）",
+            self.endpoint_len, self.digest_len, self.per_lookup, self.per_hash, self.extra_hashes
+        )
+    }
+}
+
+// テーブルファイルに保存するヘッダ
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableHeader {
+    // 生成パラメータ
+    pub params: TableParams,
+    // チェーン数
+    pub chain_count: u64,
+    // 生成日時（UNIX時間の秒、不明な場合は 0）
+    pub created_at: u64,
+    // 生成を途中で中断したテーブル（パスワードリストの一部のチェーンしか含まない）
+    #[serde(default)]
+    pub partial: bool,
+    // 開始点の保存方法（番号で保存している場合は、番号から開始プレインテキストを復元する元）
+    #[serde(default)]
+    pub start_source: StartSource,
+}
+
+impl TableHeader {
+    // 現在時刻で新しいヘッダを作成
+    pub fn new(params: TableParams, chain_count: u64) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        TableHeader {
+            params,
+            chain_count,
+            created_at,
+            partial: false,
+            start_source: StartSource::Plaintext,
+        }
+    }
+}
+
+// レインボーテーブル
+// 終端ハッシュを固定長のバイト列として昇順に並べ、二分探索で照合する
+pub struct RainbowTable {
+    pub header: TableHeader,
+    chains: ChainStore,
+    // 開始点をパスワードリストの行番号で保存している場合に、開始プレインテキストを復元するリスト
+    wordlist: Option<Arc<Wordlist>>,
+}
+
+// チェーンの格納先
+enum ChainStore {
+    // メモリ上に展開したチェーン
+    Owned {
+        digest_len: usize,
+        // 昇順に並べた終端ハッシュ（digest_len バイトずつ連結）
+        endpoints: Vec<u8>,
+        starts: OwnedStarts,
+    },
+    // メモリマップ形式のファイルを展開せずに参照するチェーン
+    Mapped(MappedChains),
+    // 圧縮形式のファイルを展開せずに参照するチェーン
+    Compressed(CompressedChains),
+}
+
+// メモリ上に展開した開始点
+enum OwnedStarts {
+    // i 番目のチェーンの開始プレインテキストは data[offsets[i]..offsets[i + 1]]
+    Text { offsets: Vec<usize>, data: String },
+    // 開始点の番号（意味はヘッダの start_source による）
+    Index(Vec<u64>),
+}
+
+// 格納されている開始点（開始プレインテキスト、またはそれを復元する番号）
+#[derive(Debug, PartialEq)]
+pub(crate) enum StoredStart<'a> {
+    Text(&'a [u8]),
+    Index(u64),
+}
+
+impl RainbowTable {
+    // （終端ハッシュ, 開始プレインテキスト）の組からテーブルを作成
+    // 終端ハッシュが重複したチェーンは policy に従って残し、その件数を報告する
+    pub fn from_chains(
+        mut header: TableHeader,
+        digest_len: usize,
+        chains: Vec<(Vec<u8>, String)>,
+        policy: CollisionPolicy,
+    ) -> Result<(Self, CollisionReport)> {
+        if let Some((end_hash, _)) = chains.iter().find(|(e, _)| e.len() != digest_len) {
+            return Err(Error::WrongDigestLength {
+                expected: digest_len,
+                actual: end_hash.len(),
+            });
+        }
+        // 先頭8バイトを整数として比較し、同じ場合のみ全体を比較する（同じ終端ハッシュは出現順）
+        let mut order: Vec<(u64, usize)> = chains
+            .iter()
+            .enumerate()
+            .map(|(i, (end_hash, _))| (endpoint_prefix(end_hash), i))
+            .collect();
+        order.sort_unstable_by(|a, b| {
+            a.0.cmp(&b.0)
+                .then_with(|| chains[a.1].0.cmp(&chains[b.1].0))
+                .then(a.1.cmp(&b.1))
+        });
+
+        let mut report = CollisionReport {
+            chains: chains.len(),
+            ..CollisionReport::default()
+        };
+        let mut endpoints = Vec::with_capacity(chains.len() * digest_len);
+        let mut start_offsets = Vec::with_capacity(chains.len() + 1);
+        let mut start_data = String::new();
+        start_offsets.push(0);
+        for group in order.chunk_by(|a, b| chains[a.1].0 == chains[b.1].0) {
+            // 同じ開始プレインテキストのチェーンは完全に同一なので、最初の1本だけを数える
+            let mut seen = HashSet::with_capacity(group.len());
+            let unique: Vec<usize> = group
+                .iter()
+                .map(|&(_, i)| i)
+                .filter(|&i| seen.insert(chains[i].1.as_str()))
+                .collect();
+            report.duplicate_starts += group.len() - unique.len();
+            report.merged_chains += unique.len() - 1;
+
+            let kept = match policy {
+                CollisionPolicy::KeepFirst => &unique[..1],
+                CollisionPolicy::KeepLast => &unique[unique.len() - 1..],
+                CollisionPolicy::KeepAll => &unique[..],
+            };
+            for &i in kept {
+                let (end_hash, start_text) = &chains[i];
+                endpoints.extend_from_slice(end_hash);
+                start_data.push_str(start_text);
+                start_offsets.push(start_data.len());
+            }
+        }
+
+        report.stored = start_offsets.len() - 1;
+        header.chain_count = report.stored as u64;
+        header.start_source = StartSource::Plaintext;
+        let starts = OwnedStarts::Text {
+            offsets: start_offsets,
+            data: start_data,
+        };
+        Ok((
+            Self::new(
+                header,
+                ChainStore::Owned {
+                    digest_len,
+                    endpoints,
+                    starts,
+                },
+            ),
+            report,
+        ))
+    }
+
+    // （終端ハッシュ, 開始点の番号）の組からテーブルを作成（ヘッダの start_source は番号の意味に合わせておく）
+    // 終端ハッシュが重複したチェーンはすべて残す
+    pub(crate) fn from_indexed_chains(
+        mut header: TableHeader,
+        digest_len: usize,
+        mut chains: Vec<(Vec<u8>, u64)>,
+    ) -> Result<Self> {
+        if let Some((end_hash, _)) = chains.iter().find(|(e, _)| e.len() != digest_len) {
+            return Err(Error::WrongDigestLength {
+                expected: digest_len,
+                actual: end_hash.len(),
+            });
+        }
+        chains.sort_unstable();
+        chains.dedup();
+        let mut endpoints = Vec::with_capacity(chains.len() * digest_len);
+        let mut indices = Vec::with_capacity(chains.len());
+        for (end_hash, index) in chains {
+            endpoints.extend_from_slice(&end_hash);
+            indices.push(index);
+        }
+        header.chain_count = indices.len() as u64;
+        let starts = OwnedStarts::Index(indices);
+        Ok(Self::new(
+            header,
+            ChainStore::Owned {
+                digest_len,
+                endpoints,
+                starts,
+            },
+        ))
+    }
+
+    // 保存したテーブルから読み込んだ、終端ハッシュの昇順に並んだチェーンでテーブルを作成
+    // 並べ替えと重複の確認はせず、昇順に並んでいることだけを確かめる
+    pub(crate) fn from_sorted_texts<'a>(
+        header: TableHeader,
+        digest_len: usize,
+        endpoints: Vec<u8>,
+        start_texts: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self> {
+        let mut offsets = vec![0];
+        let mut data = String::new();
+        for start_text in start_texts {
+            data.push_str(start_text);
+            offsets.push(data.len());
+        }
+        Self::from_sorted(
+            header,
+            digest_len,
+            endpoints,
+            OwnedStarts::Text { offsets, data },
+        )
+    }
+
+    // 開始点の番号で保存したテーブルの from_sorted_texts
+    pub(crate) fn from_sorted_indices(
+        header: TableHeader,
+        digest_len: usize,
+        endpoints: Vec<u8>,
+        indices: Vec<u64>,
+    ) -> Result<Self> {
+        Self::from_sorted(header, digest_len, endpoints, OwnedStarts::Index(indices))
+    }
+
+    fn from_sorted(
+        header: TableHeader,
+        digest_len: usize,
+        endpoints: Vec<u8>,
+        starts: OwnedStarts,
+    ) -> Result<Self> {
+        let chain_count = match &starts {
+            OwnedStarts::Text { offsets, .. } => offsets.len() - 1,
+            OwnedStarts::Index(indices) => indices.len(),
+        };
+        if endpoints.len() != chain_count * digest_len {
+            return Err(Error::CorruptTable(
+                "終端ハッシュの領域が壊れています".to_string(),
+            ));
+        }
+        if digest_len != 0 && !endpoints.chunks(digest_len).is_sorted() {
+            return Err(Error::CorruptTable(
+                "終端ハッシュが昇順に並んでいません".to_string(),
+            ));
+        }
+        Ok(Self::new(
+            header,
+            ChainStore::Owned {
+                digest_len,
+                endpoints,
+                starts,
+            },
+        ))
+    }
+
+    // メモリマップ形式のファイルから読み取ったチェーンでテーブルを作成
+    pub(crate) fn from_mapped(header: TableHeader, chains: MappedChains) -> Self {
+        Self::new(header, ChainStore::Mapped(chains))
+    }
+
+    // 圧縮形式のファイルから読み取ったチェーンでテーブルを作成
+    pub(crate) fn from_compressed(header: TableHeader, chains: CompressedChains) -> Self {
+        Self::new(header, ChainStore::Compressed(chains))
+    }
+
+    fn new(header: TableHeader, chains: ChainStore) -> Self {
+        RainbowTable {
+            header,
+            chains,
+            wordlist: None,
+        }
+    }
+
+    pub fn params(&self) -> &TableParams {
+        &self.header.params
+    }
+
+    // チェーン数
+    pub fn len(&self) -> usize {
+        match &self.chains {
+            ChainStore::Owned {
+                starts: OwnedStarts::Text { offsets, .. },
+                ..
+            } => offsets.len() - 1,
+            ChainStore::Owned {
+                starts: OwnedStarts::Index(indices),
+                ..
+            } => indices.len(),
+            ChainStore::Mapped(mapped) => mapped.len(),
+            ChainStore::Compressed(compressed) => compressed.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // 終端ハッシュのバイト長
+    pub fn digest_len(&self) -> usize {
+        match &self.chains {
+            ChainStore::Owned { digest_len, .. } => *digest_len,
+            ChainStore::Mapped(mapped) => mapped.digest_len(),
+            ChainStore::Compressed(compressed) => compressed.digest_len(),
+        }
+    }
+
+    // ファイルを展開せずに（メモリマップ形式または圧縮形式のまま）照合するテーブルか
+    pub fn is_mapped(&self) -> bool {
+        !matches!(self.chains, ChainStore::Owned { .. })
+    }
+
+    // i 番目（終端ハッシュの昇順）のチェーンの終端ハッシュ
+    // 圧縮形式ではチェーンの属するバケットを復号する
+    pub fn endpoint(&self, i: usize) -> Cow<'_, [u8]> {
+        match &self.chains {
+            ChainStore::Owned {
+                digest_len,
+                endpoints,
+                ..
+            } => Cow::Borrowed(&endpoints[i * digest_len..(i + 1) * digest_len]),
+            ChainStore::Mapped(mapped) => Cow::Borrowed(mapped.endpoint(i)),
+            ChainStore::Compressed(compressed) => Cow::Owned(compressed.endpoint(i)),
+        }
+    }
+
+    // i 番目（終端ハッシュの昇順）のチェーンの開始プレインテキスト
+    // 番号で保存している場合はパスワードリストまたはキースペースから復元する
+    // （復元できない場合は空文字列、メモリマップしたファイルの不正なUTF-8は置換文字に置き換える）
+    pub fn start(&self, i: usize) -> Cow<'_, str> {
+        self.start_text(self.stored_start(i))
+    }
+
+    // 格納されている開始点を開始プレインテキストにする
+    fn start_text<'a>(&'a self, start: StoredStart<'a>) -> Cow<'a, str> {
+        match start {
+            StoredStart::Text(start_text) => String::from_utf8_lossy(start_text),
+            StoredStart::Index(index) => Cow::Owned(self.resolve_start(index).unwrap_or_default()),
+        }
+    }
+
+    // i 番目のチェーンの格納されている開始点
+    pub(crate) fn stored_start(&self, i: usize) -> StoredStart<'_> {
+        match &self.chains {
+            ChainStore::Owned {
+                starts: OwnedStarts::Text { offsets, data },
+                ..
+            } => StoredStart::Text(&data.as_bytes()[offsets[i]..offsets[i + 1]]),
+            ChainStore::Owned {
+                starts: OwnedStarts::Index(indices),
+                ..
+            } => StoredStart::Index(indices[i]),
+            ChainStore::Mapped(mapped) => mapped.start(i),
+            ChainStore::Compressed(compressed) => compressed.start(i),
+        }
+    }
+
+    // 開始点の番号から開始プレインテキストを復元
+    fn resolve_start(&self, index: u64) -> Option<String> {
+        let keyspace = &self.header.params.keyspace;
+        match &self.header.start_source {
+            StartSource::Wordlist(_) => {
+                let wordlist = self.wordlist.as_ref()?;
+                match wordlist.line(index) {
+                    Some(line) => Some(String::from_utf8_lossy(line).into_owned()),
+                    None => keyspace.nth((index - wordlist.lines()) as u128),
+                }
+            }
+            _ => keyspace.nth(index as u128),
+        }
+    }
+
+    // 開始点の復元に必要なパスワードリスト（行番号で保存している場合のみ）
+    pub fn required_wordlist(&self) -> Option<&WordlistFingerprint> {
+        match &self.header.start_source {
+            StartSource::Wordlist(fingerprint) => Some(fingerprint),
+            _ => None,
+        }
+    }
+
+    // 開始点を復元するパスワードリストを設定する（生成に使ったものと異なる場合はエラー）
+    // 行番号で保存していないテーブルでは何もしない
+    pub fn attach_wordlist(&mut self, wordlist: Arc<Wordlist>) -> Result<()> {
+        if let Some(fingerprint) = self.required_wordlist() {
+            wordlist.check(fingerprint)?;
+            self.wordlist = Some(wordlist);
+        }
+        Ok(())
+    }
+
+    // 開始点を復元できるか確認（行番号で保存していてパスワードリストが未設定の場合はエラー）
+    pub fn check_starts(&self) -> Result<()> {
+        match self.required_wordlist() {
+            Some(fingerprint) if self.wordlist.is_none() => Err(Error::InvalidParameter(format!(
+                "開始点の復元にパスワードリスト {}（{} 行）が必要です",
+                fingerprint.name, fingerprint.lines
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    // 開始点の保存方法を変えたテーブルを作成（メモリ上に展開する）
+    // 番号にする場合、wordlist を指定するとその行番号（リストにない開始点はキースペースの通し番号 + 行数）、
+    // 指定しない場合はキースペースの通し番号で保存する
+    pub fn with_start_storage(
+        &self,
+        storage: StartStorage,
+        wordlist: Option<Arc<Wordlist>>,
+    ) -> Result<RainbowTable> {
+        self.check_starts()?;
+        let header = self.header.clone();
+        if storage == StartStorage::Plaintext {
+            let chains = self
+                .iter()
+                .map(|(end_hash, start_text)| (end_hash.into_owned(), start_text.into_owned()))
+                .collect();
+            let policy = CollisionPolicy::KeepAll;
+            return Ok(Self::from_chains(header, self.digest_len(), chains, policy)?.0);
+        }
+
+        let keyspace = &self.header.params.keyspace;
+        let line_indices: HashMap<&[u8], u64> = wordlist
+            .as_deref()
+            .map(Wordlist::line_indices)
+            .unwrap_or_default();
+        let keyspace_offset = wordlist.as_ref().map_or(0, |wordlist| wordlist.lines());
+        let chains = self
+            .iter()
+            .map(|(end_hash, start_text)| {
+                let index = match line_indices.get(start_text.as_bytes()) {
+                    Some(&line) => Some(line),
+                    None => keyspace
+                        .index_of(&start_text)
+                        .and_then(|index| u64::try_from(index).ok())
+                        .and_then(|index| index.checked_add(keyspace_offset)),
+                };
+                let index = index.ok_or_else(|| {
+                    Error::InvalidParameter(format!(
+                        "開始点 {:?} がパスワードリストにもキースペースにもないため番号で保存できません",
+                        start_text
+                    ))
+                })?;
+                Ok((end_hash.into_owned(), index))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut header = header;
+        header.start_source = match &wordlist {
+            Some(wordlist) => StartSource::Wordlist(wordlist.fingerprint().clone()),
+            None => StartSource::Keyspace,
+        };
+        let mut table = Self::from_indexed_chains(header, self.digest_len(), chains)?;
+        table.wordlist = wordlist;
+        Ok(table)
+    }
+
+    // 終端ハッシュを先頭 endpoint_len バイトに切り詰めたテーブルを作成（メモリ上に展開する）
+    // 先頭が一致するチェーンはすべて残し、照合時は誤警報としてチェーンをたどって確かめる
+    pub fn with_endpoint_len(&self, endpoint_len: usize) -> Result<RainbowTable> {
+        if endpoint_len == 0 || endpoint_len > self.digest_len() {
+            return Err(Error::InvalidParameter(format!(
+                "終端ハッシュのバイト長は 1..={} で指定してください: {}",
+                self.digest_len(),
+                endpoint_len
+            )));
+        }
+        let header = self.header.clone();
+        let mut table = if self.header.start_source.is_indexed() {
+            let chains = self
+                .stored_chains()
+                .map(|(end_hash, start)| match start {
+                    StoredStart::Index(index) => Ok((end_hash[..endpoint_len].to_vec(), index)),
+                    StoredStart::Text(_) => Err(Error::CorruptTable(
+                        "開始点の番号の代わりに開始プレインテキストがあります".to_string(),
+                    )),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Self::from_indexed_chains(header, endpoint_len, chains)?
+        } else {
+            let chains = self
+                .iter()
+                .map(|(end_hash, start_text)| {
+                    (end_hash[..endpoint_len].to_vec(), start_text.into_owned())
+                })
+                .collect();
+            let policy = CollisionPolicy::KeepAll;
+            Self::from_chains(header, endpoint_len, chains, policy)?.0
+        };
+        table.wordlist = self.wordlist.clone();
+        Ok(table)
+    }
+
+    // 終端ハッシュの切り詰めによる誤警報の見積もり（ハッシュアルゴリズムが不明な場合は None）
+    pub fn false_alarm_estimate(&self) -> Option<FalseAlarmEstimate> {
+        let digest_len = algorithm_by_name(&self.params().algorithm)?.digest_len();
+        let endpoint_len = self.digest_len();
+        // 完全な終端ハッシュでも起こる一致の分を差し引く
+        let chains = self.len() as f64;
+        let matches = |len: usize| chains / 256f64.powi(len as i32);
+        let per_lookup = (matches(endpoint_len) - matches(digest_len)).max(0.0);
+        let chain_length = self.params().chain_length as f64;
+        let per_hash = per_lookup * chain_length;
+        Some(FalseAlarmEstimate {
+            endpoint_len,
+            digest_len,
+            per_lookup,
+            per_hash,
+            extra_hashes: per_hash * chain_length,
+        })
+    }
+
+    // （終端ハッシュ, 開始プレインテキスト）を終端ハッシュの昇順に列挙
+    pub fn iter(&self) -> impl Iterator<Item = (Cow<'_, [u8]>, Cow<'_, str>)> + '_ {
+        self.stored_chains()
+            .map(move |(end_hash, start)| (end_hash, self.start_text(start)))
+    }
+
+    // （終端ハッシュ, 格納されている開始点）を終端ハッシュの昇順に列挙（テーブルの書き出し用）
+    // 圧縮形式ではバケットごとに1回だけ復号する
+    pub(crate) fn stored_chains(
+        &self,
+    ) -> Box<dyn Iterator<Item = (Cow<'_, [u8]>, StoredStart<'_>)> + '_> {
+        match &self.chains {
+            ChainStore::Compressed(compressed) => Box::new(
+                compressed
+                    .chains()
+                    .map(|(end_hash, start)| (Cow::Owned(end_hash), start)),
+            ),
+            _ => Box::new((0..self.len()).map(move |i| (self.endpoint(i), self.stored_start(i)))),
+        }
+    }
+
+    // 終端ハッシュを二分探索し、一致するチェーンの開始プレインテキストを列挙する
+    // 終端ハッシュを切り詰めたテーブルでは、保存している先頭のバイトだけを比べる
+    // 圧縮形式では終端ハッシュの属するバケットだけを復号して探す
+    pub fn find(&self, end_hash: &[u8]) -> impl Iterator<Item = Cow<'_, str>> + '_ {
+        let end_hash = &end_hash[..self.digest_len().min(end_hash.len())];
+        let matches = match &self.chains {
+            ChainStore::Compressed(compressed) => compressed.find(end_hash),
+            _ => self.binary_search(end_hash),
+        };
+        matches.map(move |i| self.start(i))
+    }
+
+    fn binary_search(&self, end_hash: &[u8]) -> Range<usize> {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if *self.endpoint(mid) < *end_hash {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let mut end = low;
+        while end < self.len() && *self.endpoint(end) == *end_hash {
+            end += 1;
+        }
+        low..end
+    }
+
+    // テーブルに記録されたキースペースとリダクションの変換器を作成
+    pub fn reducer(&self) -> Result<Reducer> {
+        self.header.params.reducer()
+    }
+
+    // 使用中のパラメータとヘッダが一致するか確認し、異なる場合はエラーを返す
+    pub fn check_params(&self, expected: &TableParams) -> Result<()> {
+        let mismatches = self.header.params.mismatches(expected);
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::TableMismatch(format!(
+                "生成パラメータが異なります（テーブル ≠ 指定値）: {}",
+                mismatches.join(", ")
+            )))
+        }
+    }
+}
+
+// 終端ハッシュの先頭8バイト（足りない場合は0で埋める）をビッグエンディアンの整数として取り出す
+fn endpoint_prefix(end_hash: &[u8]) -> u64 {
+    let mut prefix = [0u8; 8];
+    let len = end_hash.len().min(8);
+    prefix[..len].copy_from_slice(&end_hash[..len]);
+    u64::from_be_bytes(prefix)
+}
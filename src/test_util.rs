@@ -0,0 +1,43 @@
+// テスト用の小さなテーブルを作る補助関数
+use crate::generate::{generate_rainbow_table_from_reader, GenerateOptions};
+use crate::hash::Sha1Hash;
+use crate::keyspace::Keyspace;
+use crate::progress::ProgressStyle;
+use crate::table::{RainbowTable, TableParams};
+
+// 英小文字1〜6文字、チェーン長50の短いパラメータ
+pub(crate) fn params() -> TableParams {
+    TableParams {
+        keyspace: Keyspace::lowercase(1, 6).unwrap(),
+        chain_length: 50,
+        ..TableParams::default()
+    }
+}
+
+// キースペースから飛び飛びに取り出した count 行のパスワードリスト
+pub(crate) fn wordlist(count: usize) -> String {
+    let keyspace = params().keyspace;
+    (0..count as u128)
+        .map(|i| keyspace.nth(i * 7919 + 100).unwrap() + "\n")
+        .collect()
+}
+
+// count 行のパスワードリストからテーブルを生成
+pub(crate) fn table(count: usize, options: GenerateOptions) -> RainbowTable {
+    let options = GenerateOptions {
+        progress: ProgressStyle::Silent,
+        ..options
+    };
+    let wordlist = wordlist(count);
+    generate_rainbow_table_from_reader(&Sha1Hash, &params(), wordlist.as_bytes(), &options)
+        .unwrap()
+        .0
+}
+
+// （終端ハッシュ, 開始プレインテキスト）の一覧
+pub(crate) fn chains(table: &RainbowTable) -> Vec<(Vec<u8>, String)> {
+    table
+        .iter()
+        .map(|(end_hash, start_text)| (end_hash.into_owned(), start_text.into_owned()))
+        .collect()
+}